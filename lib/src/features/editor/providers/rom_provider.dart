@@ -3,6 +3,8 @@ import 'package:gba_forge/src/rust/api.dart';
 
 // State to hold the currently loaded ROM info
 class RomState {
+  // Handle of the ROM's session in the native library, passed to every api call
+  final int? session;
   final String? filePath;
   final String? gameTitle;
   final String? gameCode;
@@ -10,6 +12,7 @@ class RomState {
   final String? error;
 
   RomState({
+    this.session,
     this.filePath,
     this.gameTitle,
     this.gameCode,
@@ -18,6 +21,7 @@ class RomState {
   });
 
   RomState copyWith({
+    int? session,
     String? filePath,
     String? gameTitle,
     String? gameCode,
@@ -25,6 +29,7 @@ class RomState {
     String? error,
   }) {
     return RomState(
+      session: session ?? this.session,
       filePath: filePath ?? this.filePath,
       gameTitle: gameTitle ?? this.gameTitle,
       gameCode: gameCode ?? this.gameCode,
@@ -41,12 +46,16 @@ class RomNotifier extends StateNotifier<RomState> {
     state = state.copyWith(isLoading: true, error: null);
     try {
       // 1. Load Rom
-      final title = await loadRom(path: path);
+      final session = await loadRom(path: path);
 
-      // 2. Fetch Header Info to get Code
+      // 2. Fetch the title from the session list
+      final sessions = await getOpenSessions();
+      final title = sessions.firstWhere((s) => s.session == session).title;
+
+      // 3. Fetch Header Info to get Code
       String code = "UNKNOWN";
       try {
-        final info = await getRomHeaderInfo();
+        final info = await getRomHeaderInfo(session: session);
         // Format is "Code: XXXX, Title: YYYY"
         final parts = info.split(',');
         if (parts.isNotEmpty) {
@@ -60,6 +69,7 @@ class RomNotifier extends StateNotifier<RomState> {
       }
 
       state = state.copyWith(
+        session: session,
         filePath: path,
         gameTitle: title,
         gameCode: code,
@@ -73,7 +83,9 @@ class RomNotifier extends StateNotifier<RomState> {
   Future<void> saveRomFile(String path) async {
     state = state.copyWith(isLoading: true, error: null);
     try {
-      await saveRom(outputPath: path);
+      final session = state.session;
+      if (session == null) throw Exception("No ROM loaded");
+      await saveRom(session: session, outputPath: path);
       state = state.copyWith(isLoading: false);
     } catch (e) {
       state = state.copyWith(isLoading: false, error: e.toString());
@@ -89,7 +89,11 @@ class _HomeScreenState extends ConsumerState<HomeScreen> {
 
       if (offset == null) throw Exception("Invalid Offset Format");
 
-      final bytes = await renderMapPreview(mapHeaderPtr: offset);
+      final session = ref.read(romProvider).session;
+      if (session == null) throw Exception("No ROM loaded");
+
+      final bytes =
+          await renderMapPreview(session: session, mapHeaderPtr: offset);
       setState(() {
         _mapPreview = bytes;
       });
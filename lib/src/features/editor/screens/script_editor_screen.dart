@@ -1,23 +1,29 @@
 import 'package:flutter/material.dart';
+import 'package:flutter_riverpod/flutter_riverpod.dart';
 import 'package:gba_forge/src/rust/api.dart';
 import 'package:gba_forge/src/rust/scripting.dart';
+import '../providers/rom_provider.dart';
 
-class ScriptEditorScreen extends StatefulWidget {
+class ScriptEditorScreen extends ConsumerStatefulWidget {
   const ScriptEditorScreen({super.key});
 
   @override
-  State<ScriptEditorScreen> createState() => _ScriptEditorScreenState();
+  ConsumerState<ScriptEditorScreen> createState() => _ScriptEditorScreenState();
 }
 
-class _ScriptEditorScreenState extends State<ScriptEditorScreen> {
+class _ScriptEditorScreenState extends ConsumerState<ScriptEditorScreen> {
   List<ScriptCommand>? _script;
   bool _loading = false;
 
   Future<void> _loadScript() async {
     setState(() => _loading = true);
     try {
+      final session = ref.read(romProvider).session;
+      if (session == null) throw Exception("No ROM loaded");
+
       // Mock offset for testing
-      final script = await disassembleScript(offset: 0x800000);
+      final script =
+          await disassembleScript(session: session, offset: 0x800000);
       setState(() => _script = script);
     } catch (e) {
       if (mounted) {
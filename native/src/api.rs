@@ -1,4 +1,5 @@
-use crate::state::{RomState, APP_STATE};
+use crate::profile::GameProfile;
+use crate::state::{check_range, RomState, SessionId, SessionInfo, OFFSET_DATABASE, SESSIONS};
 use crate::structures::RomHeader;
 use anyhow::{Context, Result};
 use binrw::BinRead;
@@ -6,45 +7,117 @@ use std::fs;
 use std::io::Cursor;
 
 // This function is called from Flutter
-pub fn load_rom(path: String) -> Result<String> {
+/// Opens a ROM in a new session and returns the session handle.
+pub fn load_rom(path: String) -> Result<SessionId> {
+    let state = open_rom(&path)?;
+
+    // Register the session
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to acquire write lock"))?;
+    Ok(sessions.open(state))
+}
+
+/// Closes a session, discarding any unsaved modifications.
+pub fn close_rom(session: SessionId) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to acquire write lock"))?;
+    sessions.close(session)?;
+    Ok(())
+}
+
+/// The open sessions, oldest first.
+pub fn get_open_sessions() -> Result<Vec<SessionInfo>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to acquire read lock"))?;
+    Ok(sessions
+        .iter()
+        .map(|(session, state)| state.info(session))
+        .collect())
+}
+
+/// Offsets saved next to a ROM whose tables were moved: "hack.gba" -> "hack.ini".
+fn offsets_path(rom_path: &str) -> std::path::PathBuf {
+    std::path::Path::new(rom_path).with_extension("ini")
+}
+
+fn open_rom(path: &str) -> Result<RomState> {
     // 1. Read file from disk
-    let data = fs::read(&path).context("Failed to read ROM file")?;
+    let data = fs::read(path).context("Failed to read ROM file")?;
 
     // 2. Parse Header
     let mut reader = Cursor::new(&data);
     let header = RomHeader::read(&mut reader).context("Failed to parse ROM Header")?;
 
-    // 3. Validation (Basic check)
-    // 3. Validation (Relaxed)
-    // We no longer error on unknown codes, just log or proceed.
-    // if header.game_code != "BPRE" && header.game_code != "BPEE" {
-    //    println!("Warning: Unknown Game Code: {}", header.game_code);
-    // }
+    // 3. Identify the game and revision
+    let profile = GameProfile::detect(&header, &data)?;
 
-    let game_title = header.game_title.clone();
+    let mut state = RomState::new(data, header, profile);
 
-    // 4. Update Global State
-    {
-        let mut state = APP_STATE
-            .write()
-            .map_err(|_| anyhow::anyhow!("Failed to acquire write lock"))?;
-        *state = Some(RomState::new(data, header));
+    // 4. Offsets saved with the ROM take precedence over the offset database
+    let offsets_file = offsets_path(path);
+    if offsets_file.exists() {
+        let text = fs::read_to_string(&offsets_file).context("Failed to read offset file")?;
+        let mut saved = OffsetDatabase::default();
+        saved
+            .load_ini(&text)
+            .with_context(|| format!("Invalid offset file {}", offsets_file.display()))?;
+        if let Some(offsets) = saved.lookup(&state.profile.game_code, state.profile.revision) {
+            state.offsets = Some(offsets);
+        }
+    }
+
+    Ok(state)
+}
+
+/// Loads a base ROM and applies IPS, UPS or BPS patches to it in order.
+/// Each patch becomes one change-set of pending modifications, so its bytes can
+/// be inspected, reverted or undone like any other edit. Returns the new session handle.
+pub fn load_rom_with_patches(path: String, patch_paths: Vec<String>) -> Result<SessionId> {
+    let mut state = open_rom(&path)?;
+
+    for patch_path in &patch_paths {
+        let name = std::path::Path::new(patch_path)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| patch_path.clone());
+        let patch = fs::read(patch_path).with_context(|| format!("Failed to read {}", name))?;
+
+        let patched =
+            patch_rom(state.view(), &patch).with_context(|| format!("Failed to apply {}", name))?;
+        if patched.len() < state.view().len() {
+            anyhow::bail!(
+                "{} shrinks the ROM, which cannot be kept as modifications",
+                name
+            );
+        }
+
+        let runs = differences(state.view(), &patched);
+        state.transaction(&format!("Apply {}", name), |state| {
+            for (start, end) in runs {
+                state.apply_patch(start as u32, patched[start..end].to_vec())?;
+            }
+            Ok(())
+        })?;
     }
 
-    Ok(format!("Loaded: {}", game_title))
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to acquire write lock"))?;
+    Ok(sessions.open(state))
 }
 
-pub fn get_rom_header_info() -> Result<String> {
-    let state = APP_STATE
+pub fn get_rom_header_info(session: SessionId) -> Result<String> {
+    let sessions = SESSIONS
         .read()
         .map_err(|_| anyhow::anyhow!("Failed to acquire read lock"))?;
-    match &*state {
-        Some(s) => Ok(format!(
-            "Code: {}, Title: {}",
-            s.header.game_code, s.header.game_title
-        )),
-        None => Err(anyhow::anyhow!("No ROM loaded")),
-    }
+    let s = sessions.get(session)?;
+    Ok(format!(
+        "Code: {}, Title: {}",
+        s.header.game_code, s.header.game_title
+    ))
 }
 
 // Helper to resolve GBA pointer (0x08xxxxxx -> 0x0xxxxxxx)
@@ -56,38 +129,84 @@ fn resolve_pointer(ptr: u32) -> Result<usize> {
     Ok((ptr & 0x01FFFFFF) as usize)
 }
 
-use crate::compression::decompress_lz77;
-use crate::graphics::decode_4bpp_tile;
+use crate::compatibility::{
+    decode_bitfield, encode_bitfield, machine_label, tutor_label, LearnableMove, MACHINE_COUNT,
+    TM_COMPAT_SIZE,
+};
+use crate::compression::{compress_lz77, decompress_lz77};
+use crate::diff::{
+    changed_ranges, compare, compare_table, ChangeStatus, RomDiff, StructureChange, StructureKind,
+};
+use crate::encounters::{
+    encode_header, encode_new_table, encode_slots, read_map_encounters, read_wild_headers,
+    EncounterKind, MapEncounters, WILD_HEADER_SIZE, WILD_INFO_SIZE,
+};
+use crate::expansion::{expand_table, ExpandableTable, RelocatedTable};
+use crate::graphics::{decode_4bpp_tile, encode_png};
+use crate::history::HistoryInfo;
+use crate::items::{item_constant, ItemInfo, ITEM_SIZE};
+use crate::map_renderer::{animation_overrides, draw_object, load_map_tilesets, render_blocks};
+use crate::maps::{
+    block_count, encode_blocks, read_blocks, read_map_banks, region_patches, resize_grid,
+    validate_map_size, MapBlock, MapBlockGrid, ResizeAnchor,
+};
+use crate::metatiles::{read_metatile, AttributeFormat, Metatile, METATILE_SIZE};
+use crate::modifications::{Allocation, PendingWrite};
+use crate::moves::{
+    description_ptr_offset, read_move, read_move_name, MoveInfo, MOVE_NAME_SIZE, MOVE_SIZE,
+};
+use crate::offsets::{GameOffsets, OffsetDatabase};
+use crate::overworld::{
+    decode_overworld_sprite, find_sprite_palette, read_object_events, OverworldSprite,
+    OVERWORLD_INFO_SIZE,
+};
+use crate::patches::{create_patch, differences, patch_rom, PatchFormat};
+use crate::pokedex::{
+    decode_dex_entry, description_ptrs, encode_dex_entry, DexOrder, PokedexFormat, PokedexInfo,
+};
+use crate::project::{Project, ProjectMetadata};
 use crate::scripting::{disassemble, ScriptCommand};
-use crate::structures::{MapHeader, MapLayout, TilesetHeader};
+use crate::species::{
+    decode_evolutions, encode_evolutions, encode_learnset, read_learnset, Evolution, LevelUpMove,
+    EVOLUTIONS_PER_SPECIES, EVOLUTION_SIZE,
+};
+use crate::sprites::{
+    footprint_to_image, image_to_tiles, palette_bytes, palette_colors, palette_from_indices,
+    quantize_sprites, read_compressed, read_palette, tiles_to_image, SpriteKind, ICON_SIZE,
+    ICON_WIDTH_TILES, SPRITE_ENTRY_SIZE, SPRITE_WIDTH_TILES,
+};
+use crate::structures::{BaseStats, Item, Trainer, WildHeader, WildInfo};
+use crate::structures::{MapHeader, MapLayout, ObjectEventTemplate, TilesetHeader};
+use crate::tables::{encode_entry, entry_offset, read_entry, read_u16_table};
+use crate::text::{decode_text, encode_fixed_text, encode_text, text_size};
+use crate::tileset_anim::{find_tile_animations, TileAnimation, DEFAULT_FRAME_INTERVAL};
+use crate::trainers::{decode_party, encode_party, PartyFormat, TrainerInfo, TRAINER_SIZE};
+use binrw::BinWrite;
+use std::collections::HashMap;
 
-pub fn disassemble_script(offset: u32) -> Result<Vec<ScriptCommand>> {
-    let state_guard = APP_STATE
+pub fn disassemble_script(session: SessionId, offset: u32) -> Result<Vec<ScriptCommand>> {
+    let sessions = SESSIONS
         .read()
         .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
-    let state = state_guard
-        .as_ref()
-        .ok_or(anyhow::anyhow!("No ROM loaded"))?;
+    let state = sessions.get(session)?;
 
     let real_offset = resolve_pointer(offset)?;
-    if real_offset >= state.data.len() {
+    if real_offset >= state.view().len() {
         anyhow::bail!("Offset out of bounds");
     }
 
-    disassemble(&state.data, real_offset)
+    disassemble(state.view(), real_offset)
 }
 
-pub fn render_map_preview(map_header_ptr: u32) -> Result<Vec<u8>> {
-    let state_guard = APP_STATE
+pub fn render_map_preview(session: SessionId, map_header_ptr: u32) -> Result<Vec<u8>> {
+    let sessions = SESSIONS
         .read()
         .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
-    let state = state_guard
-        .as_ref()
-        .ok_or(anyhow::anyhow!("No ROM loaded"))?;
+    let state = sessions.get(session)?;
 
     // 1. Read Map Header
     let map_header_offset = resolve_pointer(map_header_ptr)?;
-    let mut reader = Cursor::new(&state.data);
+    let mut reader = Cursor::new(state.view());
     reader.set_position(map_header_offset as u64);
     let map_header = MapHeader::read(&mut reader).context("Failed to read MapHeader")?;
 
@@ -123,18 +242,18 @@ pub fn render_map_preview(map_header_ptr: u32) -> Result<Vec<u8>> {
     let raw_gfx = if tileset.is_compressed == 1 {
         // Read from ROM at offset
         // We need to slice from gfx_offset to end?
-        if gfx_offset >= state.data.len() {
+        if gfx_offset >= state.view().len() {
             anyhow::bail!("Graphics ptr out of bounds");
         }
-        decompress_lz77(&state.data[gfx_offset..])?
+        decompress_lz77(&state.view()[gfx_offset..])?
     } else {
         // Uncompressed, assume fixed size? or read until something?
         // Usually 4bpp tiles. Let's read 128 tiles (128 * 32 bytes = 4KB)
         let size = 128 * 32;
-        if gfx_offset + size > state.data.len() {
+        if gfx_offset + size > state.view().len() {
             anyhow::bail!("Graphics ptr out of bounds for uncompressed read");
         }
-        state.data[gfx_offset..gfx_offset + size].to_vec()
+        state.view()[gfx_offset..gfx_offset + size].to_vec()
     };
 
     // 6. Decode first 128 tiles into a grid (16 tiles wide, 8 high)
@@ -186,49 +305,1944 @@ fn calculate_header_checksum(data: &[u8]) -> u8 {
 }
 
 /// Saves the current ROM state to a new file.
-pub fn save_rom(output_path: String) -> Result<String> {
-    let state_guard = APP_STATE
+/// If tables were moved or expanded, the offsets are saved next to it (see `offsets_path`)
+/// so they are found again when the ROM is loaded.
+pub fn save_rom(session: SessionId, output_path: String) -> Result<String> {
+    let sessions = SESSIONS
         .read()
         .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
-    let state = state_guard
-        .as_ref()
-        .ok_or(anyhow::anyhow!("No ROM loaded"))?;
+    let state = sessions.get(session)?;
+
+    // Apply pending modifications and recalculate the header checksum
+    let new_data = output_rom(state);
 
-    // Clone data to modify it
-    let mut new_data = state.data.clone();
+    use std::fs;
+    fs::write(&output_path, &new_data).context("Failed to write ROM file")?;
 
-    // 1. Apply any pending modifications (from BTreeMap)
-    for (offset, bytes) in &state.modifications {
-        let start = *offset as usize;
-        let end = start + bytes.len();
-        if end > new_data.len() {
-            // Handle expansion
-            new_data.resize(end, 0xFF);
+    if let Some(offsets) = &state.offsets {
+        let (game_code, revision) = (&state.profile.game_code, state.profile.revision);
+        let database_offsets = OFFSET_DATABASE
+            .read()
+            .map_err(|_| anyhow::anyhow!("Failed to lock"))?
+            .lookup(game_code, revision);
+        if database_offsets.as_ref() != Some(offsets) {
+            let section = OffsetDatabase::section_name(game_code, revision);
+            let name = format!("{} saved by gbaforge", state.profile.name());
+            fs::write(offsets_path(&output_path), offsets.to_ini(&section, &name)?)
+                .context("Failed to write offset file")?;
         }
-        new_data[start..end].copy_from_slice(bytes);
     }
 
-    // 2. Recalculate Checksum
+    Ok(format!("Saved to {}", output_path))
+}
+
+/// The ROM as `save_rom` writes it: pending modifications applied, header checksum fixed.
+fn output_rom(state: &RomState) -> Vec<u8> {
+    let mut new_data = state.view().to_vec();
     let checksum = calculate_header_checksum(&new_data);
     new_data[0xBD] = checksum;
+    new_data
+}
 
-    // 3. Write to Disk
-    use std::fs;
-    fs::write(&output_path, &new_data).context("Failed to write ROM file")?;
+/// Writes the difference between the loaded ROM and the modified one as a patch file.
+pub fn export_patch(
+    session: SessionId,
+    format: PatchFormat,
+    output_path: String,
+) -> Result<String> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
 
-    Ok(format!("Saved to {}", output_path))
+    let patch = create_patch(format, &state.data, &output_rom(state))?;
+    std::fs::write(&output_path, &patch).context("Failed to write patch file")?;
+
+    Ok(format!("Exported {:?} patch to {}", format, output_path))
+}
+
+pub fn apply_patch(session: SessionId, offset: u32, data: Vec<u8>) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(&format!("Patch at {:08x}", offset), |state| {
+        // In a real scenario, we might want to check if data fits or needs repointing.
+        check_range(offset, data.len())?;
+        // Raw writes that straddle the edge of pending data are most likely mistakes;
+        // failing the transaction rolls the write back
+        if let Some((start, len)) = state.apply_patch(offset, data)?.first() {
+            anyhow::bail!(
+                "Patch at {:08x} partially overlaps pending changes at {:08x}-{:08x}",
+                offset,
+                start,
+                start + len
+            );
+        }
+        Ok(())
+    })
+}
+
+/// Pending (unsaved) bytes within `offset..offset + length`.
+pub fn get_pending_modifications(
+    session: SessionId,
+    offset: u32,
+    length: u32,
+) -> Result<Vec<PendingWrite>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    Ok(state.pending(offset, length))
+}
+
+/// Discards pending changes within `offset..offset + length`.
+pub fn revert_modifications(session: SessionId, offset: u32, length: u32) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(
+        &format!("Revert {:08x}-{:08x}", offset, offset + length),
+        |state| {
+            state.revert(offset, length)?;
+            Ok(())
+        },
+    )
+}
+
+/// Follows a MapHeader pointer to its MapLayout.
+fn read_map_layout(data: &[u8], map_header_ptr: u32) -> Result<MapLayout> {
+    read_map_layout_with_offset(data, map_header_ptr).map(|(layout, _)| layout)
+}
+
+fn read_map_header(data: &[u8], map_header_ptr: u32) -> Result<MapHeader> {
+    let mut reader = Cursor::new(data);
+    reader.set_position(resolve_pointer(map_header_ptr)? as u64);
+    MapHeader::read(&mut reader).context("Failed to read MapHeader")
+}
+
+/// Same as `read_map_layout`, also returning the layout's ROM offset.
+fn read_map_layout_with_offset(data: &[u8], map_header_ptr: u32) -> Result<(MapLayout, u32)> {
+    let map_header = read_map_header(data, map_header_ptr)?;
+
+    let layout_offset = resolve_pointer(map_header.map_data_ptr)?;
+    let mut reader = Cursor::new(data);
+    reader.set_position(layout_offset as u64);
+    let layout = MapLayout::read(&mut reader).context("Failed to read MapLayout")?;
+    Ok((layout, layout_offset as u32))
+}
+
+/// Returns the full block grid (metatile, collision, elevation) of a map.
+pub fn get_map_blocks(session: SessionId, map_header_ptr: u32) -> Result<MapBlockGrid> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let layout = read_map_layout(state.view(), map_header_ptr)?;
+    read_blocks(state.view(), &layout)
+}
+
+pub fn get_map_block(session: SessionId, map_header_ptr: u32, x: u32, y: u32) -> Result<MapBlock> {
+    let grid = get_map_blocks(session, map_header_ptr)?;
+    if x >= grid.width || y >= grid.height {
+        anyhow::bail!(
+            "Block ({}, {}) is outside the {}x{} map",
+            x,
+            y,
+            grid.width,
+            grid.height
+        );
+    }
+    Ok(grid.blocks[(y * grid.width + x) as usize])
+}
+
+/// Writes `region` into the map with its top-left corner at (x, y), clipping at the map edges.
+pub fn paste_map_blocks(
+    session: SessionId,
+    map_header_ptr: u32,
+    x: u32,
+    y: u32,
+    region: MapBlockGrid,
+) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction("Edit map blocks", |state| {
+        let layout = read_map_layout(state.view(), map_header_ptr)?;
+        for (offset, bytes) in region_patches(&layout, x, y, &region)? {
+            state.apply_patch(offset, bytes)?;
+        }
+        Ok(())
+    })
+}
+
+pub fn set_map_block(
+    session: SessionId,
+    map_header_ptr: u32,
+    x: u32,
+    y: u32,
+    block: MapBlock,
+) -> Result<()> {
+    paste_map_blocks(
+        session,
+        map_header_ptr,
+        x,
+        y,
+        MapBlockGrid {
+            width: 1,
+            height: 1,
+            blocks: vec![block],
+        },
+    )
+}
+
+/// Fills a `width` x `height` rectangle starting at (x, y) with `block`.
+/// The rectangle is clipped at the map edges.
+pub fn fill_map_blocks(
+    session: SessionId,
+    map_header_ptr: u32,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    block: MapBlock,
+) -> Result<()> {
+    let layout = {
+        let sessions = SESSIONS
+            .read()
+            .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+        read_map_layout(sessions.get(session)?.view(), map_header_ptr)?
+    };
+    let width = width.min(layout.width.saturating_sub(x));
+    let height = height.min(layout.height.saturating_sub(y));
+
+    paste_map_blocks(
+        session,
+        map_header_ptr,
+        x,
+        y,
+        MapBlockGrid {
+            width,
+            height,
+            blocks: vec![block; block_count(width, height)?],
+        },
+    )
+}
+
+/// Resizes a map's block grid.
+/// The old blocks are kept at `anchor` and new space is filled with `fill`.
+/// A grid that no longer fits its old location is moved to free space; `free_old`
+/// overwrites the old location with 0xFF so it can be reused.
+/// Returns the (possibly new) pointer to the block data.
+pub fn resize_map(
+    session: SessionId,
+    map_header_ptr: u32,
+    new_width: u32,
+    new_height: u32,
+    anchor: ResizeAnchor,
+    fill: MapBlock,
+    free_old: bool,
+) -> Result<u32> {
+    validate_map_size(new_width, new_height)?;
+
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(
+        &format!("Resize map to {}x{}", new_width, new_height),
+        |state| {
+            let (mut layout, layout_offset) =
+                read_map_layout_with_offset(state.view(), map_header_ptr)?;
+            let old_grid = read_blocks(state.view(), &layout)?;
+            let new_grid = resize_grid(&old_grid, new_width, new_height, anchor, fill);
+
+            let old_offset = layout.map_data_ptr & 0x01FFFFFF;
+            let old_size = old_grid.blocks.len() * 2;
+            let new_bytes = encode_blocks(&new_grid);
+
+            let new_offset = if new_bytes.len() <= old_size {
+                // Fits in place; only the unused tail may be released
+                if free_old && new_bytes.len() < old_size {
+                    let tail = old_offset + new_bytes.len() as u32;
+                    state.apply_patch(tail, vec![0xFF; old_size - new_bytes.len()])?;
+                }
+                old_offset
+            } else {
+                let offset = state.allocate(new_bytes.len())?;
+                if free_old {
+                    state.apply_patch(old_offset, vec![0xFF; old_size])?;
+                }
+                offset
+            };
+            state.apply_patch(new_offset, new_bytes)?;
+
+            layout.width = new_width;
+            layout.height = new_height;
+            layout.map_data_ptr = 0x08000000 | new_offset;
+            let mut layout_bytes = Cursor::new(Vec::new());
+            layout
+                .write_le(&mut layout_bytes)
+                .context("Failed to encode MapLayout")?;
+            state.apply_patch(layout_offset, layout_bytes.into_inner())?;
+
+            Ok(layout.map_data_ptr)
+        },
+    )
+}
+
+pub(crate) fn read_tileset_header(data: &[u8], tileset_ptr: u32) -> Result<TilesetHeader> {
+    let mut reader = Cursor::new(data);
+    reader.set_position(resolve_pointer(tileset_ptr)? as u64);
+    TilesetHeader::read(&mut reader).context("Failed to read TilesetHeader")
+}
+
+/// Returns every metatile of a tileset, with its tiles and behaviour attributes.
+pub fn get_metatiles(session: SessionId, tileset_ptr: u32) -> Result<Vec<Metatile>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let tileset = read_tileset_header(state.view(), tileset_ptr)?;
+    let format = AttributeFormat::from_game_code(&state.header.game_code);
+    let metatiles_offset = resolve_pointer(tileset.metatiles_ptr)?;
+    let behavior_offset = resolve_pointer(tileset.attributes_ptr(format))?;
+
+    (0..format.metatile_count(tileset.is_secondary != 0))
+        .map(|i| read_metatile(state.view(), metatiles_offset, behavior_offset, i, format))
+        .collect()
+}
+
+pub fn get_metatile(session: SessionId, tileset_ptr: u32, index: u32) -> Result<Metatile> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let tileset = read_tileset_header(state.view(), tileset_ptr)?;
+    let format = AttributeFormat::from_game_code(&state.header.game_code);
+    read_metatile(
+        state.view(),
+        resolve_pointer(tileset.metatiles_ptr)?,
+        resolve_pointer(tileset.attributes_ptr(format))?,
+        index as usize,
+        format,
+    )
+}
+
+/// Overwrites the tiles and attributes of metatile `index` in a tileset.
+pub fn set_metatile(
+    session: SessionId,
+    tileset_ptr: u32,
+    index: u32,
+    metatile: Metatile,
+) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(&format!("Edit metatile {}", index), |state| {
+        let tileset = read_tileset_header(state.view(), tileset_ptr)?;
+        let format = AttributeFormat::from_game_code(&state.header.game_code);
+        if index as usize >= format.metatile_count(tileset.is_secondary != 0) {
+            anyhow::bail!("Metatile index {} out of range for this tileset", index);
+        }
+
+        let metatiles_offset = tileset.metatiles_ptr & 0x01FFFFFF;
+        let behavior_offset = tileset.attributes_ptr(format) & 0x01FFFFFF;
+        state.apply_patch(
+            metatiles_offset + index * METATILE_SIZE as u32,
+            metatile.encode_tiles()?,
+        )?;
+        state.apply_patch(
+            behavior_offset + index * format.size() as u32,
+            metatile.attributes.to_bytes(format),
+        )?;
+        Ok(())
+    })
+}
+
+/// Lists the tile animations (water, flowers, ...) a tileset installs.
+pub fn get_tileset_animations(session: SessionId, tileset_ptr: u32) -> Result<Vec<TileAnimation>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let tileset = read_tileset_header(state.view(), tileset_ptr)?;
+    let format = AttributeFormat::from_game_code(&state.header.game_code);
+    Ok(find_tile_animations(
+        state.view(),
+        tileset.callback_ptr(format),
+    ))
+}
+
+/// Longest animation loop rendered, in frames.
+const MAX_ANIMATION_FRAMES: usize = 64;
+
+/// Renders one full loop of a map's tile animations.
+/// Returns the frames and the number of game ticks each one lasts.
+fn render_map_animation_images(
+    session: SessionId,
+    map_header_ptr: u32,
+) -> Result<(Vec<image::RgbaImage>, u32)> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let layout = read_map_layout(state.view(), map_header_ptr)?;
+    let grid = read_blocks(state.view(), &layout)?;
+    let format = AttributeFormat::from_game_code(&state.header.game_code);
+    let tilesets = load_map_tilesets(state.view(), &layout, format)?;
+
+    let mut animations = find_tile_animations(state.view(), tilesets.primary.callback_ptr(format));
+    animations.extend(find_tile_animations(
+        state.view(),
+        tilesets.secondary.callback_ptr(format),
+    ));
+
+    // Every animation changes frame on a multiple of the intervals' gcd
+    let step = animations
+        .iter()
+        .map(|a| a.interval.max(1) as usize)
+        .reduce(gcd)
+        .unwrap_or(DEFAULT_FRAME_INTERVAL as usize);
+
+    // The loop repeats once every animation is back at its first frame
+    let period = animations
+        .iter()
+        .map(|a| a.interval.max(1) as usize * a.frames.len().max(1))
+        .fold(step, |acc, n| (acc / gcd(acc, n)).saturating_mul(n));
+    let frame_count = (period / step).min(MAX_ANIMATION_FRAMES);
+
+    let frames = (0..frame_count)
+        .map(|i| {
+            let tick = (i * step) as u32;
+            let overrides = animation_overrides(state.view(), &animations, tick);
+            render_blocks(&grid, &tilesets, &overrides)
+        })
+        .collect();
+    Ok((frames, step as u32))
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Renders a map with its animated tiles as PNG frames, for APNG assembly or playback in Flutter.
+pub fn render_map_animation_frames(
+    session: SessionId,
+    map_header_ptr: u32,
+) -> Result<Vec<Vec<u8>>> {
+    render_map_animation_images(session, map_header_ptr)?
+        .0
+        .into_iter()
+        .map(|frame| {
+            let mut png_data = Vec::new();
+            image::DynamicImage::ImageRgba8(frame)
+                .write_to(
+                    &mut Cursor::new(&mut png_data),
+                    image::ImageOutputFormat::Png,
+                )
+                .context("Failed to encode animation frame as PNG")?;
+            Ok(png_data)
+        })
+        .collect()
+}
+
+/// Renders a map with its animated tiles as a looping GIF.
+pub fn render_map_animation_gif(session: SessionId, map_header_ptr: u32) -> Result<Vec<u8>> {
+    use image::codecs::gif::{GifEncoder, Repeat};
+    use image::{Delay, Frame};
+
+    let (frames, ticks) = render_map_animation_images(session, map_header_ptr)?;
+    // The game runs at 60 ticks a second
+    let delay = Delay::from_numer_denom_ms(ticks * 1000, 60);
+
+    let mut gif_data = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut gif_data);
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .context("Failed to configure GIF")?;
+        encoder
+            .encode_frames(
+                frames
+                    .into_iter()
+                    .map(|f| Frame::from_parts(f, 0, 0, delay)),
+            )
+            .context("Failed to encode map animation as GIF")?;
+    }
+    Ok(gif_data)
+}
+
+/// Returns the wild encounter data of every map that has any.
+pub fn get_wild_encounters(session: SessionId) -> Result<Vec<MapEncounters>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let table_offset = state.offsets()?.wild_pokemon as usize;
+    read_wild_headers(state.view(), table_offset)?
+        .iter()
+        .map(|header| read_map_encounters(state.view(), header))
+        .collect()
+}
+
+pub fn get_map_wild_encounters(
+    session: SessionId,
+    bank: u8,
+    map: u8,
+) -> Result<Option<MapEncounters>> {
+    Ok(get_wild_encounters(session)?
+        .into_iter()
+        .find(|e| e.bank == bank && e.map == map))
+}
+
+/// Writes a map's wild encounter data.
+/// Existing tables are overwritten in place. Tables the map did not have yet are
+/// allocated in free space, and a map without any encounter data gets a new header,
+/// which moves the header table to free space.
+pub fn set_map_wild_encounters(session: SessionId, encounters: MapEncounters) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(
+        &format!(
+            "Edit wild encounters of map {}.{}",
+            encounters.bank, encounters.map
+        ),
+        |state| {
+            // Reads go through the view, which includes tables created earlier in this session
+            let table_offset = state.offsets()?.wild_pokemon;
+            let headers = read_wild_headers(state.view(), table_offset as usize)?;
+            let position = headers
+                .iter()
+                .position(|h| h.bank == encounters.bank && h.map == encounters.map);
+
+            let mut header = match position {
+                Some(i) => headers[i].clone(),
+                None => WildHeader {
+                    bank: encounters.bank,
+                    map: encounters.map,
+                    padding: 0,
+                    land_ptr: 0,
+                    water_ptr: 0,
+                    rock_smash_ptr: 0,
+                    fishing_ptr: 0,
+                },
+            };
+
+            for kind in EncounterKind::ALL {
+                let ptr = kind.table_ptr(&header);
+                match encounters.table(kind) {
+                    Some(table) if ptr != 0 => {
+                        let mut reader = Cursor::new(state.view());
+                        reader.set_position(resolve_pointer(ptr)? as u64);
+                        let info =
+                            WildInfo::read(&mut reader).context("Failed to read WildInfo")?;
+                        state.apply_patch(ptr & 0x01FFFFFF, vec![table.encounter_rate])?;
+                        state
+                            .apply_patch(info.mons_ptr & 0x01FFFFFF, encode_slots(table, kind)?)?;
+                    }
+                    Some(table) => {
+                        let size = WILD_INFO_SIZE + kind.slot_count() * 4;
+                        let offset = state.allocate(size)?;
+                        state.apply_patch(offset, encode_new_table(table, kind, offset)?)?;
+                        kind.set_table_ptr(&mut header, 0x08000000 | offset);
+                    }
+                    None => kind.set_table_ptr(&mut header, 0),
+                }
+            }
+
+            match position {
+                Some(i) => {
+                    let header_offset = table_offset + (i * WILD_HEADER_SIZE) as u32;
+                    state.apply_patch(header_offset, encode_header(&header)?)?;
+                }
+                None => {
+                    // Rebuild the table with the new header before the terminator
+                    let terminator_start = table_offset as usize + headers.len() * WILD_HEADER_SIZE;
+                    let data = state.view();
+                    let mut table = data[table_offset as usize..terminator_start].to_vec();
+                    table.extend(encode_header(&header)?);
+                    table.extend_from_slice(
+                        &data[terminator_start..terminator_start + WILD_HEADER_SIZE],
+                    );
+
+                    let new_offset = state.relocate(table_offset, table)?;
+                    if let Some(offsets) = state.offsets.as_mut() {
+                        offsets.wild_pokemon = new_offset;
+                    }
+                }
+            }
+
+            Ok(())
+        },
+    )
+}
+
+const BASE_STATS_SIZE: usize = 28;
+
+fn read_base_stats(data: &[u8], offsets: &GameOffsets, species: u16) -> Result<BaseStats> {
+    let offset = entry_offset(
+        offsets.base_stats,
+        species as u32,
+        BASE_STATS_SIZE,
+        offsets.species_count,
+    )?;
+    read_entry(data, offset, BASE_STATS_SIZE)
+}
+
+pub fn get_base_stats(session: SessionId, species: u16) -> Result<BaseStats> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    read_base_stats(state.view(), state.offsets()?, species)
+}
+
+pub fn set_base_stats(session: SessionId, species: u16, stats: BaseStats) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(
+        &format!("Edit base stats of species {}", species),
+        |state| {
+            let offsets = state.offsets()?;
+            let offset = entry_offset(
+                offsets.base_stats,
+                species as u32,
+                BASE_STATS_SIZE,
+                offsets.species_count,
+            )?;
+            state.apply_patch(offset, encode_entry(&stats)?)?;
+            Ok(())
+        },
+    )
+}
+
+pub fn get_move(session: SessionId, move_id: u16) -> Result<MoveInfo> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    read_move(state.view(), state.offsets()?, move_id)
+}
+
+/// Names of all moves, indexed by move id.
+pub fn get_move_names(session: SessionId) -> Result<Vec<String>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let offsets = state.offsets()?;
+    (0..offsets.move_count as u16)
+        .map(|id| read_move_name(state.view(), offsets, id))
+        .collect()
+}
+
+/// Writes a move's battle data, name and description.
+/// A description longer than the current one is moved to free space.
+pub fn set_move(session: SessionId, move_id: u16, info: MoveInfo) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(&format!("Edit move {}", move_id), |state| {
+        let offsets = state.offsets()?.clone();
+        let current = read_move(state.view(), &offsets, move_id)?;
+
+        let move_offset =
+            entry_offset(offsets.moves, move_id as u32, MOVE_SIZE, offsets.move_count)?;
+        let name_offset = entry_offset(
+            offsets.move_names,
+            move_id as u32,
+            MOVE_NAME_SIZE,
+            offsets.move_count,
+        )?;
+        let name = encode_fixed_text(&info.name, MOVE_NAME_SIZE)?;
+        state.apply_patch(move_offset, encode_entry(&info.data)?)?;
+        state.apply_patch(name_offset, name)?;
+
+        if info.description != current.description {
+            let Some(ptr_offset) = description_ptr_offset(&offsets, move_id) else {
+                anyhow::bail!("Move 0 has no description");
+            };
+            let ptr: u32 = read_entry(state.view(), ptr_offset, 4)?;
+            let old_size = text_size(state.view(), resolve_pointer(ptr)?);
+            state.write_pointed_data(
+                ptr_offset,
+                ptr & 0x01FFFFFF,
+                old_size,
+                encode_text(&info.description)?,
+            )?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Reads a trainer entry and the raw bytes of its party.
+fn read_trainer_entry(data: &[u8], offset: u32) -> Result<(Trainer, Vec<u8>)> {
+    let trainer: Trainer = read_entry(data, offset, TRAINER_SIZE)?;
+    let format = PartyFormat::from_flags(trainer.party_flags);
+    let start = resolve_pointer(trainer.party_ptr)?;
+    let end = start + trainer.party_size as usize * format.entry_size();
+    if end > data.len() {
+        anyhow::bail!("Trainer party out of bounds");
+    }
+    let party = data[start..end].to_vec();
+    Ok((trainer, party))
+}
+
+fn read_trainer(data: &[u8], offsets: &GameOffsets, trainer_id: u16) -> Result<TrainerInfo> {
+    let offset = entry_offset(
+        offsets.trainers,
+        trainer_id as u32,
+        TRAINER_SIZE,
+        offsets.trainer_count,
+    )?;
+    let (trainer, party_bytes) = read_trainer_entry(data, offset)?;
+    let format = PartyFormat::from_flags(trainer.party_flags);
+    let party = decode_party(&party_bytes, format, trainer.party_size as usize)?;
+    Ok(TrainerInfo::from_trainer(&trainer, party))
+}
+
+pub fn get_trainer(session: SessionId, trainer_id: u16) -> Result<TrainerInfo> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    read_trainer(state.view(), state.offsets()?, trainer_id)
+}
+
+/// Writes a trainer and its party.
+/// The party is rewritten in place when it still fits; a party that grew (more
+/// Pokémon, or a format with items/moves) is moved to free space and repointed.
+pub fn set_trainer(session: SessionId, trainer_id: u16, info: TrainerInfo) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(&format!("Edit trainer {}", trainer_id), |state| {
+        let offsets = state.offsets()?;
+        let offset = entry_offset(
+            offsets.trainers,
+            trainer_id as u32,
+            TRAINER_SIZE,
+            offsets.trainer_count,
+        )?;
+        let (current, old_party) = read_trainer_entry(state.view(), offset)?;
+        let party = encode_party(&info.party, info.party_format)?;
+
+        let party_offset =
+            state.write_data(current.party_ptr & 0x01FFFFFF, old_party.len(), party)?;
+
+        let trainer = info.to_trainer(0x08000000 | party_offset)?;
+        state.apply_patch(offset, encode_entry(&trainer)?)?;
+        Ok(())
+    })
+}
+
+fn read_item(data: &[u8], offsets: &GameOffsets, item_id: u16) -> Result<(u32, Item)> {
+    let offset = entry_offset(offsets.items, item_id as u32, ITEM_SIZE, offsets.item_count)?;
+    Ok((offset, read_entry(data, offset, ITEM_SIZE)?))
+}
+
+pub fn get_item(session: SessionId, item_id: u16) -> Result<ItemInfo> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let (_, item) = read_item(state.view(), state.offsets()?, item_id)?;
+    ItemInfo::from_item(state.view(), &item)
+}
+
+/// Names of all items, indexed by item id.
+pub fn get_item_names(session: SessionId) -> Result<Vec<String>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let offsets = state.offsets()?;
+    (0..offsets.item_count as u16)
+        .map(|id| {
+            let (_, item) = read_item(state.view(), offsets, id)?;
+            Ok(decode_text(&item.name))
+        })
+        .collect()
+}
+
+/// Writes an item entry. A description longer than the current one is moved to free space.
+pub fn set_item(session: SessionId, item_id: u16, info: ItemInfo) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(&format!("Edit item {}", item_id), |state| {
+        let (offset, item) = read_item(state.view(), state.offsets()?, item_id)?;
+        let current = ItemInfo::from_item(state.view(), &item)?;
+
+        let mut entry = info.to_item(item_id)?;
+        if info.description != current.description {
+            let old_offset = item.description_ptr & 0x01FFFFFF;
+            let old_size = if item.description_ptr == 0 {
+                0
+            } else {
+                text_size(state.view(), old_offset as usize)
+            };
+            let new_offset =
+                state.write_data(old_offset, old_size, encode_text(&info.description)?)?;
+            entry.description_ptr = 0x08000000 | new_offset;
+        }
+        state.apply_patch(offset, encode_entry(&entry)?)?;
+        Ok(())
+    })
+}
+
+/// Disassembles a script as XSE-style source lines, with item ids shown as item constants.
+pub fn disassemble_script_text(session: SessionId, offset: u32) -> Result<Vec<String>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let real_offset = resolve_pointer(offset)?;
+    if real_offset >= state.view().len() {
+        anyhow::bail!("Offset out of bounds");
+    }
+
+    // Item names are optional: unknown games still disassemble with raw ids
+    let offsets = state.offsets.as_ref();
+    let item_name = |id: u16| {
+        let offsets = offsets?;
+        let (_, item) = read_item(state.view(), offsets, id).ok()?;
+        Some(item_constant(&decode_text(&item.name)))
+    };
+
+    Ok(disassemble(state.view(), real_offset)?
+        .iter()
+        .map(|command| command.to_source(&item_name))
+        .collect())
+}
+
+const EVOLUTION_ENTRY_SIZE: usize = EVOLUTIONS_PER_SPECIES * EVOLUTION_SIZE;
+
+fn read_evolutions(data: &[u8], offsets: &GameOffsets, species: u16) -> Result<Vec<Evolution>> {
+    let offset = entry_offset(
+        offsets.evolutions,
+        species as u32,
+        EVOLUTION_ENTRY_SIZE,
+        offsets.species_count,
+    )? as usize;
+    if offset + EVOLUTION_ENTRY_SIZE > data.len() {
+        anyhow::bail!("Evolution entry out of bounds");
+    }
+    Ok(decode_evolutions(
+        &data[offset..offset + EVOLUTION_ENTRY_SIZE],
+    ))
+}
+
+pub fn get_evolutions(session: SessionId, species: u16) -> Result<Vec<Evolution>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    read_evolutions(state.view(), state.offsets()?, species)
+}
+
+/// Replaces a species' evolutions (up to 5).
+pub fn set_evolutions(session: SessionId, species: u16, evolutions: Vec<Evolution>) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(
+        &format!("Edit evolutions of species {}", species),
+        |state| {
+            let offsets = state.offsets()?;
+            let offset = entry_offset(
+                offsets.evolutions,
+                species as u32,
+                EVOLUTION_ENTRY_SIZE,
+                offsets.species_count,
+            )?;
+            state.apply_patch(offset, encode_evolutions(&evolutions)?)?;
+            Ok(())
+        },
+    )
+}
+
+fn read_species_learnset(
+    data: &[u8],
+    offsets: &GameOffsets,
+    species: u16,
+) -> Result<Vec<LevelUpMove>> {
+    let ptr_offset = entry_offset(offsets.learnsets, species as u32, 4, offsets.species_count)?;
+    let ptr: u32 = read_entry(data, ptr_offset, 4)?;
+    read_learnset(data, resolve_pointer(ptr)?)
+}
+
+pub fn get_learnset(session: SessionId, species: u16) -> Result<Vec<LevelUpMove>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    read_species_learnset(state.view(), state.offsets()?, species)
+}
+
+/// Replaces a species' level-up learnset.
+/// A learnset that grew is moved to free space and its pointer updated.
+pub fn set_learnset(session: SessionId, species: u16, moves: Vec<LevelUpMove>) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(&format!("Edit learnset of species {}", species), |state| {
+        let offsets = state.offsets()?;
+        let ptr_offset = entry_offset(offsets.learnsets, species as u32, 4, offsets.species_count)?;
+        let ptr: u32 = read_entry(state.view(), ptr_offset, 4)?;
+        let old_offset = resolve_pointer(ptr)?;
+        let old_size = (read_learnset(state.view(), old_offset)?.len() + 1) * 2;
+
+        state.write_pointed_data(
+            ptr_offset,
+            old_offset as u32,
+            old_size,
+            encode_learnset(&moves)?,
+        )?;
+        Ok(())
+    })
+}
+
+/// Move taught by each TM/HM, TM01 first and HM08 last.
+pub fn get_tm_moves(session: SessionId) -> Result<Vec<u16>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let offsets = state.offsets()?;
+    read_u16_table(state.view(), offsets.tm_moves, MACHINE_COUNT)
+}
+
+/// Changes the move taught by TM/HM `slot` (0-49 TMs, 50-57 HMs).
+pub fn set_tm_move(session: SessionId, slot: u16, move_id: u16) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(
+        &format!("Change move of {}", machine_label(slot as usize)),
+        |state| {
+            let offsets = state.offsets()?;
+            let offset = entry_offset(offsets.tm_moves, slot as u32, 2, MACHINE_COUNT as u32)?;
+            state.apply_patch(offset, move_id.to_le_bytes().to_vec())?;
+            Ok(())
+        },
+    )
+}
+
+/// TMs/HMs a species can learn, with the moves they teach.
+pub fn get_tm_compatibility(session: SessionId, species: u16) -> Result<Vec<LearnableMove>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let offsets = state.offsets()?;
+    let offset = entry_offset(
+        offsets.tm_compatibility,
+        species as u32,
+        TM_COMPAT_SIZE,
+        offsets.species_count,
+    )? as usize;
+    let moves = read_u16_table(state.view(), offsets.tm_moves, MACHINE_COUNT)?;
+    let Some(bits) = state.view().get(offset..offset + TM_COMPAT_SIZE) else {
+        anyhow::bail!("TM compatibility out of bounds");
+    };
+
+    Ok(decode_bitfield(bits, MACHINE_COUNT)
+        .into_iter()
+        .map(|slot| LearnableMove {
+            slot,
+            label: machine_label(slot as usize),
+            move_id: moves[slot as usize],
+        })
+        .collect())
+}
+
+/// Sets the TM/HM slots a species can learn.
+pub fn set_tm_compatibility(session: SessionId, species: u16, slots: Vec<u16>) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(
+        &format!("Edit TM/HM compatibility of species {}", species),
+        |state| {
+            let offsets = state.offsets()?;
+            let offset = entry_offset(
+                offsets.tm_compatibility,
+                species as u32,
+                TM_COMPAT_SIZE,
+                offsets.species_count,
+            )?;
+            state.apply_patch(
+                offset,
+                encode_bitfield(&slots, MACHINE_COUNT, TM_COMPAT_SIZE)?,
+            )?;
+            Ok(())
+        },
+    )
+}
+
+/// Move taught by each move tutor.
+pub fn get_tutor_moves(session: SessionId) -> Result<Vec<u16>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let offsets = state.offsets()?;
+    read_u16_table(
+        state.view(),
+        offsets.tutor_moves,
+        offsets.tutor_count as usize,
+    )
+}
+
+pub fn set_tutor_move(session: SessionId, slot: u16, move_id: u16) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(
+        &format!("Change move of {}", tutor_label(slot as usize)),
+        |state| {
+            let offsets = state.offsets()?;
+            let offset = entry_offset(offsets.tutor_moves, slot as u32, 2, offsets.tutor_count)?;
+            state.apply_patch(offset, move_id.to_le_bytes().to_vec())?;
+            Ok(())
+        },
+    )
+}
+
+/// Tutor moves a species can learn.
+pub fn get_tutor_compatibility(session: SessionId, species: u16) -> Result<Vec<LearnableMove>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let offsets = state.offsets()?;
+    let size = offsets.tutor_compat_size();
+    let offset = entry_offset(
+        offsets.tutor_compatibility,
+        species as u32,
+        size,
+        offsets.species_count,
+    )? as usize;
+    let count = offsets.tutor_count as usize;
+    let moves = read_u16_table(state.view(), offsets.tutor_moves, count)?;
+    let Some(bits) = state.view().get(offset..offset + size) else {
+        anyhow::bail!("Tutor compatibility out of bounds");
+    };
+
+    Ok(decode_bitfield(bits, count)
+        .into_iter()
+        .map(|slot| LearnableMove {
+            slot,
+            label: tutor_label(slot as usize),
+            move_id: moves[slot as usize],
+        })
+        .collect())
+}
+
+/// Sets the tutor slots a species can learn.
+pub fn set_tutor_compatibility(session: SessionId, species: u16, slots: Vec<u16>) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(
+        &format!("Edit tutor compatibility of species {}", species),
+        |state| {
+            let offsets = state.offsets()?;
+            let size = offsets.tutor_compat_size();
+            let offset = entry_offset(
+                offsets.tutor_compatibility,
+                species as u32,
+                size,
+                offsets.species_count,
+            )?;
+            let bits = encode_bitfield(&slots, offsets.tutor_count as usize, size)?;
+            state.apply_patch(offset, bits)?;
+            Ok(())
+        },
+    )
+}
+
+/// Grows the species, move or item tables to `new_count` entries.
+/// Returns where each parallel table was moved to.
+pub fn expand_data_table(
+    session: SessionId,
+    table: ExpandableTable,
+    new_count: u32,
+) -> Result<Vec<RelocatedTable>> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(
+        &format!("Expand {:?} table to {} entries", table, new_count),
+        |state| expand_table(state, table, new_count),
+    )
+}
+
+/// Reads the Pokédex entry of national dex number `dex_number`.
+pub fn get_pokedex_entry(session: SessionId, dex_number: u16) -> Result<PokedexInfo> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let offsets = state.offsets()?;
+    let format = PokedexFormat::from_game_code(&state.header.game_code);
+    let offset = entry_offset(
+        offsets.pokedex_entries,
+        dex_number as u32,
+        format.entry_size(),
+        offsets.pokedex_count,
+    )? as usize;
+    let Some(entry) = state.view().get(offset..offset + format.entry_size()) else {
+        anyhow::bail!("Pokédex entry out of bounds");
+    };
+    decode_dex_entry(state.view(), entry, format)
+}
+
+/// Writes a Pokédex entry. Description pages that no longer fit are moved to free space.
+pub fn set_pokedex_entry(session: SessionId, dex_number: u16, info: PokedexInfo) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(&format!("Edit Pokédex entry {}", dex_number), |state| {
+        let offsets = state.offsets()?.clone();
+        let format = PokedexFormat::from_game_code(&state.header.game_code);
+        let offset = entry_offset(
+            offsets.pokedex_entries,
+            dex_number as u32,
+            format.entry_size(),
+            offsets.pokedex_count,
+        )?;
+        if info.description.len() != format.page_count() {
+            anyhow::bail!(
+                "Pokédex entries have {} description pages",
+                format.page_count()
+            );
+        }
+
+        let start = offset as usize;
+        let Some(entry) = state.view().get(start..start + format.entry_size()) else {
+            anyhow::bail!("Pokédex entry out of bounds");
+        };
+        let current = decode_dex_entry(state.view(), entry, format)?;
+
+        let mut ptrs = description_ptrs(entry, format);
+        for (page, text) in info.description.iter().enumerate() {
+            if *text == current.description[page] {
+                continue;
+            }
+            // A null page has no text to overwrite, so its new text goes to free space
+            let old = ptrs[page] & 0x01FFFFFF;
+            let old_size = if ptrs[page] == 0 {
+                0
+            } else {
+                text_size(state.view(), old as usize)
+            };
+            let new_offset = state.write_data(old, old_size, encode_text(text)?)?;
+            ptrs[page] = 0x08000000 | new_offset;
+        }
+
+        state.apply_patch(offset, encode_dex_entry(&info, format, &ptrs)?)?;
+        Ok(())
+    })
+}
+
+/// Reads a dex number table. Index 0 is species (or regional number) 1.
+pub fn get_dex_order(session: SessionId, order: DexOrder) -> Result<Vec<u16>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let offsets = state.offsets()?;
+    read_u16_table(
+        state.view(),
+        offsets.dex_order(order),
+        offsets.species_count as usize - 1,
+    )
 }
 
-pub fn apply_patch(offset: u32, data: Vec<u8>) -> Result<()> {
-    let mut state_guard = APP_STATE
+/// Sets the dex number of `index` (a species, or a regional number) in a dex number table.
+pub fn set_dex_number(
+    session: SessionId,
+    order: DexOrder,
+    index: u16,
+    dex_number: u16,
+) -> Result<()> {
+    let mut sessions = SESSIONS
         .write()
         .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
-    let state = state_guard
-        .as_mut()
-        .ok_or(anyhow::anyhow!("No ROM loaded"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(
+        &format!("Edit {:?} dex number of {}", order, index),
+        |state| {
+            let offsets = state.offsets()?;
+            if index == 0 {
+                anyhow::bail!("Dex number tables start at 1");
+            }
+            let offset = entry_offset(
+                offsets.dex_order(order),
+                index as u32 - 1,
+                2,
+                offsets.species_count - 1,
+            )?;
+            state.apply_patch(offset, dex_number.to_le_bytes().to_vec())?;
+            Ok(())
+        },
+    )
+}
+
+/// Renders a species' front or back sprite, with its normal or shiny palette, as PNG.
+/// Sprites with several frames are drawn as a vertical strip.
+pub fn render_pokemon_sprite(
+    session: SessionId,
+    species: u16,
+    kind: SpriteKind,
+) -> Result<Vec<u8>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let offsets = state.offsets()?;
+    let (sprites, palettes) = (
+        if kind.is_front() {
+            offsets.front_sprites
+        } else {
+            offsets.back_sprites
+        },
+        if kind.is_shiny() {
+            offsets.shiny_palettes
+        } else {
+            offsets.normal_palettes
+        },
+    );
+    let sprite_ptr: u32 = read_entry(
+        state.view(),
+        entry_offset(
+            sprites,
+            species as u32,
+            SPRITE_ENTRY_SIZE,
+            offsets.species_count,
+        )?,
+        4,
+    )?;
+    let palette_ptr: u32 = read_entry(
+        state.view(),
+        entry_offset(
+            palettes,
+            species as u32,
+            SPRITE_ENTRY_SIZE,
+            offsets.species_count,
+        )?,
+        4,
+    )?;
+
+    let tiles = read_compressed(state.view(), sprite_ptr)?;
+    let palette = palette_colors(&read_compressed(state.view(), palette_ptr)?);
+    if palette.len() < 16 {
+        anyhow::bail!("Sprite palette has fewer than 16 colours");
+    }
+    encode_png(tiles_to_image(&tiles, &palette, SPRITE_WIDTH_TILES))
+}
+
+/// Renders a species' menu icon (both frames) with its icon palette as PNG.
+pub fn render_pokemon_icon(session: SessionId, species: u16) -> Result<Vec<u8>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let offsets = state.offsets()?;
+    let icon_ptr: u32 = read_entry(
+        state.view(),
+        entry_offset(offsets.icons, species as u32, 4, offsets.species_count)?,
+        4,
+    )?;
+    let palette_index: u8 = read_entry(
+        state.view(),
+        entry_offset(
+            offsets.icon_palette_indices,
+            species as u32,
+            1,
+            offsets.species_count,
+        )?,
+        1,
+    )?;
+    let palette_ptr: u32 = read_entry(
+        state.view(),
+        offsets.icon_palettes + palette_index as u32 * SPRITE_ENTRY_SIZE as u32,
+        4,
+    )?;
+
+    let start = resolve_pointer(icon_ptr)?;
+    let Some(tiles) = state.view().get(start..start + ICON_SIZE) else {
+        anyhow::bail!("Icon graphics out of bounds");
+    };
+    let palette = read_palette(state.view(), palette_ptr)?;
+    encode_png(tiles_to_image(tiles, &palette, ICON_WIDTH_TILES))
+}
+
+/// Renders a species' 16x16 Pokédex footprint as PNG.
+pub fn render_pokemon_footprint(session: SessionId, species: u16) -> Result<Vec<u8>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let offsets = state.offsets()?;
+    let ptr: u32 = read_entry(
+        state.view(),
+        entry_offset(offsets.footprints, species as u32, 4, offsets.species_count)?,
+        4,
+    )?;
+    let start = resolve_pointer(ptr)?;
+    encode_png(footprint_to_image(
+        state.view().get(start..).unwrap_or_default(),
+    )?)
+}
+
+fn decode_sprite_png(png: &[u8]) -> Result<image::RgbaImage> {
+    let image = image::load_from_memory(png)
+        .context("Failed to decode PNG")?
+        .to_rgba8();
+    if image.width() != SPRITE_WIDTH_TILES * 8 || image.height() == 0 || image.height() % 64 != 0 {
+        anyhow::bail!(
+            "Sprites must be 64 pixels wide and a multiple of 64 high, got {}x{}",
+            image.width(),
+            image.height()
+        );
+    }
+    Ok(image)
+}
+
+/// Compresses `bytes` into free space and points a sprite or palette table entry at it.
+/// Sprite entries also store the uncompressed size after the pointer.
+fn write_sprite_entry(
+    state: &mut RomState,
+    entry: u32,
+    bytes: &[u8],
+    store_size: bool,
+) -> Result<()> {
+    let compressed = compress_lz77(bytes);
+    let offset = state.allocate(compressed.len())?;
+    state.apply_patch(offset, compressed)?;
 
-    // In a real scenario, we might want to check if data fits or needs repointing.
-    // For now, we update the modification map.
-    state.modifications.insert(offset, data);
+    let mut value = (0x08000000 | offset).to_le_bytes().to_vec();
+    if store_size {
+        value.extend((bytes.len() as u16).to_le_bytes());
+    }
+    state.apply_patch(entry, value)?;
     Ok(())
 }
+
+/// Inserts new front and back sprites for a species from PNG files.
+///
+/// Both sprites are quantised to one shared 16-colour palette. The shiny palette is
+/// taken from `shiny_front_png`, a recoloured copy of the front sprite; without it the
+/// shiny palette is the normal one. Graphics and palettes are compressed into free space.
+pub fn import_pokemon_sprites(
+    session: SessionId,
+    species: u16,
+    front_png: Vec<u8>,
+    back_png: Vec<u8>,
+    shiny_front_png: Option<Vec<u8>>,
+) -> Result<()> {
+    let front = decode_sprite_png(&front_png)?;
+    let back = decode_sprite_png(&back_png)?;
+    let quantized = quantize_sprites(&[&front, &back]);
+    let shiny = match shiny_front_png {
+        Some(png) => palette_from_indices(
+            &quantized.indices[0],
+            &decode_sprite_png(&png)?,
+            &quantized.palette,
+        )?,
+        None => quantized.palette.clone(),
+    };
+
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.transaction(&format!("Import sprites of species {}", species), |state| {
+        let offsets = state.offsets()?.clone();
+        let entry = |table: u32| {
+            entry_offset(
+                table,
+                species as u32,
+                SPRITE_ENTRY_SIZE,
+                offsets.species_count,
+            )
+        };
+
+        let width = SPRITE_WIDTH_TILES * 8;
+        write_sprite_entry(
+            state,
+            entry(offsets.front_sprites)?,
+            &image_to_tiles(&quantized.indices[0], width),
+            true,
+        )?;
+        write_sprite_entry(
+            state,
+            entry(offsets.back_sprites)?,
+            &image_to_tiles(&quantized.indices[1], width),
+            true,
+        )?;
+        write_sprite_entry(
+            state,
+            entry(offsets.normal_palettes)?,
+            &palette_bytes(&quantized.palette),
+            false,
+        )?;
+        write_sprite_entry(
+            state,
+            entry(offsets.shiny_palettes)?,
+            &palette_bytes(&shiny),
+            false,
+        )?;
+        Ok(())
+    })
+}
+
+/// Decodes overworld sprite `graphics_id` and renders its frames.
+fn read_overworld_sprite(
+    data: &[u8],
+    offsets: &GameOffsets,
+    graphics_id: u16,
+) -> Result<(OverworldSprite, Vec<image::RgbaImage>)> {
+    let ptr: u32 = read_entry(
+        data,
+        entry_offset(
+            offsets.overworld_sprites,
+            graphics_id as u32,
+            4,
+            offsets.overworld_sprite_count,
+        )?,
+        4,
+    )?;
+    let info = read_entry(data, ptr & 0x01FFFFFF, OVERWORLD_INFO_SIZE)?;
+    let sprite = decode_overworld_sprite(data, &info);
+
+    let palette_ptr = find_sprite_palette(data, offsets.overworld_palettes, sprite.palette_tag)?;
+    let palette = read_palette(data, palette_ptr)?;
+    let frame_size = info.size as usize;
+    let frames = sprite
+        .frames
+        .iter()
+        .map(|&frame| {
+            let start = resolve_pointer(frame)?;
+            let Some(tiles) = data.get(start..start + frame_size) else {
+                anyhow::bail!("Overworld frame out of bounds");
+            };
+            Ok(tiles_to_image(tiles, &palette, sprite.width as u32 / 8))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((sprite, frames))
+}
+
+pub fn get_overworld_sprite(session: SessionId, graphics_id: u16) -> Result<OverworldSprite> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let offsets = state.offsets()?;
+    let ptr: u32 = read_entry(
+        state.view(),
+        entry_offset(
+            offsets.overworld_sprites,
+            graphics_id as u32,
+            4,
+            offsets.overworld_sprite_count,
+        )?,
+        4,
+    )?;
+    let info = read_entry(state.view(), ptr & 0x01FFFFFF, OVERWORLD_INFO_SIZE)?;
+    Ok(decode_overworld_sprite(state.view(), &info))
+}
+
+/// Renders every frame of overworld sprite `graphics_id` as PNG.
+pub fn render_overworld_sprite_frames(
+    session: SessionId,
+    graphics_id: u16,
+) -> Result<Vec<Vec<u8>>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let (_, frames) = read_overworld_sprite(state.view(), state.offsets()?, graphics_id)?;
+    frames.into_iter().map(encode_png).collect()
+}
+
+/// Renders a map with the first frame of each person event's sprite at its position.
+/// Events whose sprite cannot be drawn (e.g. variable graphics ids) are skipped.
+pub fn render_map_with_objects(session: SessionId, map_header_ptr: u32) -> Result<Vec<u8>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let offsets = state.offsets()?;
+    let header = read_map_header(state.view(), map_header_ptr)?;
+    let layout = read_map_layout(state.view(), map_header_ptr)?;
+    let grid = read_blocks(state.view(), &layout)?;
+    let format = AttributeFormat::from_game_code(&state.header.game_code);
+    let tilesets = load_map_tilesets(state.view(), &layout, format)?;
+    let mut image = render_blocks(&grid, &tilesets, &HashMap::new());
+
+    for event in read_object_events(state.view(), header.event_data_ptr)? {
+        if let Ok((_, frames)) =
+            read_overworld_sprite(state.view(), offsets, event.graphics_id as u16)
+        {
+            if let Some(frame) = frames.first() {
+                draw_object(&mut image, frame, event.x as i32, event.y as i32);
+            }
+        }
+    }
+
+    encode_png(image)
+}
+
+/// Game, revision and checksum of the loaded ROM.
+pub fn get_game_profile(session: SessionId) -> Result<GameProfile> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    Ok(state.profile.clone())
+}
+
+/// Loads an INI offset file into the offset database and returns its section names.
+/// Open ROMs whose revision has a section in the file switch to those offsets, unless
+/// they no longer use their revision's previous offsets: tables moved or expanded in
+/// the session, or offsets from another section or an offset file, are kept.
+pub fn load_offset_database(path: String) -> Result<Vec<String>> {
+    let text = fs::read_to_string(&path).context("Failed to read offset file")?;
+    let (sections, previous) = {
+        let mut database = OFFSET_DATABASE
+            .write()
+            .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+        let previous = database.clone();
+        (database.load_ini(&text)?, previous)
+    };
+
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    for (_, state) in sessions.iter_mut() {
+        let (game_code, revision) = (&state.profile.game_code, state.profile.revision);
+        let section = OffsetDatabase::section_name(game_code, revision);
+        if sections.contains(&section) && state.offsets == previous.lookup(game_code, revision) {
+            use_offsets_locked(state, &section)?;
+        }
+    }
+    Ok(sections)
+}
+
+/// Names of every section in the offset database.
+pub fn get_offset_sections() -> Result<Vec<String>> {
+    Ok(OFFSET_DATABASE
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?
+        .section_names())
+}
+
+fn use_offsets_locked(state: &mut RomState, section: &str) -> Result<()> {
+    let offsets = OFFSET_DATABASE
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?
+        .resolve(section)?;
+    state.offsets = Some(offsets);
+    Ok(())
+}
+
+/// Uses the offsets of `section` for the session's ROM, e.g. a hack with relocated tables.
+pub fn use_offset_section(session: SessionId, section: String) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    use_offsets_locked(state, &section)
+}
+
+/// Undoes the most recent edit. Returns its description, or `None` if there is nothing to undo.
+pub fn undo(session: SessionId) -> Result<Option<String>> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.undo()
+}
+
+/// Redoes the most recently undone edit. Returns its description, or `None`.
+pub fn redo(session: SessionId) -> Result<Option<String>> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.redo()
+}
+
+/// Descriptions of the edits that can be undone and redone, most recent last.
+pub fn get_edit_history(session: SessionId) -> Result<HistoryInfo> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    Ok(state.history_info())
+}
+
+/// Groups the edits made until `end_change_group` into one undo step,
+/// e.g. "move NPC and repoint table".
+pub fn begin_change_group(session: SessionId, description: String) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.begin_group(&description);
+    Ok(())
+}
+
+pub fn end_change_group(session: SessionId) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.end_group();
+    Ok(())
+}
+
+/// Compares the ROMs open in two sessions: the byte ranges that differ, and which
+/// species, moves, items, trainers, maps and object scripts those changes belong to.
+/// Tables are compared entry by entry using each ROM's own offsets, so relocated or
+/// expanded tables still line up.
+pub fn diff_roms(before_session: SessionId, after_session: SessionId) -> Result<RomDiff> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let before = sessions.get(before_session)?;
+    let after = sessions.get(after_session)?;
+    if before.profile.game != after.profile.game {
+        anyhow::bail!(
+            "Cannot compare {} with {}",
+            before.profile.name(),
+            after.profile.name()
+        );
+    }
+
+    let ranges = changed_ranges(before.view(), after.view());
+    let structures = if ranges.is_empty() {
+        Vec::new()
+    } else {
+        diff_structures(before, after)?
+    };
+    Ok(RomDiff {
+        before: before.info(before_session).title,
+        after: after.info(after_session).title,
+        changed_bytes: ranges.iter().map(|range| range.len).sum(),
+        changed_ranges: ranges,
+        structures,
+    })
+}
+
+/// Same as `diff_roms`, as pretty-printed JSON for review.
+pub fn diff_roms_json(before_session: SessionId, after_session: SessionId) -> Result<String> {
+    let diff = diff_roms(before_session, after_session)?;
+    serde_json::to_string_pretty(&diff).context("Failed to serialise ROM diff")
+}
+
+fn diff_structures(before: &RomState, after: &RomState) -> Result<Vec<StructureChange>> {
+    let (a, b) = (before.offsets()?, after.offsets()?);
+    let (data_a, data_b) = (before.view(), after.view());
+    let species = (a.species_count, b.species_count);
+
+    let mut changes = Vec::new();
+    changes.extend(compare_table(
+        StructureKind::BaseStats,
+        "Species",
+        species,
+        |i| read_base_stats(data_a, a, i as u16),
+        |i| read_base_stats(data_b, b, i as u16),
+    )?);
+    changes.extend(compare_table(
+        StructureKind::Evolutions,
+        "Species",
+        species,
+        |i| read_evolutions(data_a, a, i as u16),
+        |i| read_evolutions(data_b, b, i as u16),
+    )?);
+    changes.extend(compare_table(
+        StructureKind::Learnset,
+        "Species",
+        species,
+        |i| read_species_learnset(data_a, a, i as u16),
+        |i| read_species_learnset(data_b, b, i as u16),
+    )?);
+    changes.extend(compare_table(
+        StructureKind::Move,
+        "Move",
+        (a.move_count, b.move_count),
+        |i| read_move(data_a, a, i as u16),
+        |i| read_move(data_b, b, i as u16),
+    )?);
+    changes.extend(compare_table(
+        StructureKind::Item,
+        "Item",
+        (a.item_count, b.item_count),
+        |i| ItemInfo::from_item(data_a, &read_item(data_a, a, i as u16)?.1),
+        |i| ItemInfo::from_item(data_b, &read_item(data_b, b, i as u16)?.1),
+    )?);
+    changes.extend(compare_table(
+        StructureKind::Trainer,
+        "Trainer",
+        (a.trainer_count, b.trainer_count),
+        |i| read_trainer(data_a, a, i as u16),
+        |i| read_trainer(data_b, b, i as u16),
+    )?);
+
+    // Maps are only compared when both ROMs' map tables are known
+    if [a.map_banks, a.map_bank_count, b.map_banks, b.map_bank_count].contains(&0) {
+        return Ok(changes);
+    }
+    let banks_a = read_map_banks(data_a, a.map_banks, a.map_bank_count)?;
+    let banks_b = read_map_banks(data_b, b.map_banks, b.map_bank_count)?;
+    for bank in 0..banks_a.len().max(banks_b.len()) {
+        let maps_a = banks_a.get(bank).map(Vec::as_slice).unwrap_or_default();
+        let maps_b = banks_b.get(bank).map(Vec::as_slice).unwrap_or_default();
+        for map in 0..maps_a.len().max(maps_b.len()) {
+            changes.extend(diff_map(
+                (data_a, maps_a.get(map).copied()),
+                (data_b, maps_b.get(map).copied()),
+                (bank << 8 | map) as u32,
+                &format!("Map {}.{}", bank, map),
+            )?);
+        }
+    }
+    Ok(changes)
+}
+
+/// Compares one map's header, layout, blocks, object events and object scripts.
+/// Parts that cannot be read (e.g. placeholder maps) are treated as absent.
+fn diff_map(
+    (data_a, ptr_a): (&[u8], Option<u32>),
+    (data_b, ptr_b): (&[u8], Option<u32>),
+    index: u32,
+    label: &str,
+) -> Result<Vec<StructureChange>> {
+    let header_a = ptr_a.and_then(|ptr| read_map_header(data_a, ptr).ok());
+    let header_b = ptr_b.and_then(|ptr| read_map_header(data_b, ptr).ok());
+    let mut changes = Vec::new();
+    changes.extend(compare(
+        StructureKind::MapHeader,
+        index,
+        label.to_string(),
+        header_a.as_ref(),
+        header_b.as_ref(),
+    )?);
+
+    let layout_a = ptr_a.and_then(|ptr| read_map_layout(data_a, ptr).ok());
+    let layout_b = ptr_b.and_then(|ptr| read_map_layout(data_b, ptr).ok());
+    let mut layout_change = compare(
+        StructureKind::MapLayout,
+        index,
+        label.to_string(),
+        layout_a.as_ref(),
+        layout_b.as_ref(),
+    )?;
+    if let (Some(layout_a), Some(layout_b)) = (&layout_a, &layout_b) {
+        if let (Ok(grid_a), Ok(grid_b)) =
+            (read_blocks(data_a, layout_a), read_blocks(data_b, layout_b))
+        {
+            let changed = grid_a
+                .blocks
+                .iter()
+                .zip(&grid_b.blocks)
+                .filter(|(a, b)| a != b)
+                .count();
+            if grid_a.width == grid_b.width && grid_a.height == grid_b.height && changed > 0 {
+                let change = layout_change.get_or_insert_with(|| StructureChange {
+                    kind: StructureKind::MapLayout,
+                    index,
+                    label: label.to_string(),
+                    status: ChangeStatus::Changed,
+                    fields: Vec::new(),
+                    summary: None,
+                });
+                change.summary = Some(format!(
+                    "{} of {} blocks changed",
+                    changed,
+                    grid_a.blocks.len()
+                ));
+            }
+        }
+    }
+    changes.extend(layout_change);
+
+    let objects_a = header_a
+        .as_ref()
+        .and_then(|header| read_object_events(data_a, header.event_data_ptr).ok());
+    let objects_b = header_b
+        .as_ref()
+        .and_then(|header| read_object_events(data_b, header.event_data_ptr).ok());
+    changes.extend(compare(
+        StructureKind::MapObjects,
+        index,
+        label.to_string(),
+        objects_a.as_ref(),
+        objects_b.as_ref(),
+    )?);
+
+    // Scripts are compared command by command. Commands such as Call, Goto and Message
+    // hold absolute pointers, so a script whose callees or text moved is reported too
+    let script = |data: &[u8], objects: &Option<Vec<ObjectEventTemplate>>, i: usize| {
+        let ptr = objects.as_ref()?.get(i)?.script_ptr;
+        if ptr == 0 {
+            return None;
+        }
+        disassemble(data, resolve_pointer(ptr).ok()?).ok()
+    };
+    let object_count = objects_a.as_ref().map_or(0, Vec::len);
+    let object_count = object_count.max(objects_b.as_ref().map_or(0, Vec::len));
+    for i in 0..object_count {
+        changes.extend(compare(
+            StructureKind::Script,
+            index,
+            format!("{} object {} script", label, i),
+            script(data_a, &objects_a, i).as_ref(),
+            script(data_b, &objects_b, i).as_ref(),
+        )?);
+    }
+    Ok(changes)
+}
+
+/// Saves the session's edits as a project file: the base ROM's checksum, the
+/// recorded edits in order, the allocation ledger and the project metadata.
+/// The ROM itself is not included, so the file can be kept in version control.
+pub fn save_project(session: SessionId, output_path: String) -> Result<String> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    let json = Project::from_state(state).to_json()?;
+    fs::write(&output_path, json).context("Failed to write project file")?;
+
+    Ok(format!("Saved project to {}", output_path))
+}
+
+/// Opens a project against its clean base ROM and returns the new session handle.
+/// Fails if the ROM is not the one the project was made from.
+pub fn open_project(project_path: String, rom_path: String) -> Result<SessionId> {
+    let text = fs::read_to_string(&project_path).context("Failed to read project file")?;
+    let project = Project::from_json(&text)?;
+    let mut state = open_rom(&rom_path)?;
+    project.restore(&mut state)?;
+
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to acquire write lock"))?;
+    Ok(sessions.open(state))
+}
+
+pub fn get_project_metadata(session: SessionId) -> Result<ProjectMetadata> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    Ok(state.metadata.clone())
+}
+
+/// Replaces the project name, notes and labels. Not part of the edit history.
+pub fn set_project_metadata(session: SessionId, metadata: ProjectMetadata) -> Result<()> {
+    let mut sessions = SESSIONS
+        .write()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get_mut(session)?;
+
+    state.metadata = metadata;
+    Ok(())
+}
+
+/// Free space claimed by edits that are still pending, by offset.
+pub fn get_allocations(session: SessionId) -> Result<Vec<Allocation>> {
+    let sessions = SESSIONS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Failed to lock"))?;
+    let state = sessions.get(session)?;
+
+    Ok(state.allocation_ledger())
+}
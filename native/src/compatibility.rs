@@ -0,0 +1,67 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// TM01-TM50 followed by HM01-HM08.
+pub const TM_COUNT: usize = 50;
+pub const MACHINE_COUNT: usize = 58;
+/// Bytes of TM/HM compatibility per species (one bit per machine).
+pub const TM_COMPAT_SIZE: usize = 8;
+
+/// A move a species can learn from a TM/HM or a move tutor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LearnableMove {
+    /// Index of the machine or tutor slot
+    pub slot: u16,
+    /// "TM05", "HM02" or "Tutor 3"
+    pub label: String,
+    pub move_id: u16,
+}
+
+pub fn machine_label(slot: usize) -> String {
+    if slot < TM_COUNT {
+        format!("TM{:02}", slot + 1)
+    } else {
+        format!("HM{:02}", slot - TM_COUNT + 1)
+    }
+}
+
+pub fn tutor_label(slot: usize) -> String {
+    format!("Tutor {}", slot + 1)
+}
+
+/// Indices of the set bits of a little-endian bitfield.
+pub fn decode_bitfield(bytes: &[u8], bit_count: usize) -> Vec<u16> {
+    (0..bit_count.min(bytes.len() * 8))
+        .filter(|&bit| bytes[bit / 8] & (1 << (bit % 8)) != 0)
+        .map(|bit| bit as u16)
+        .collect()
+}
+
+/// Encodes `slots` as a bitfield of `size` bytes.
+pub fn encode_bitfield(slots: &[u16], bit_count: usize, size: usize) -> Result<Vec<u8>> {
+    let mut bytes = vec![0u8; size];
+    for &slot in slots {
+        let bit = slot as usize;
+        if bit >= bit_count {
+            bail!("Slot {} out of range ({} slots)", slot, bit_count);
+        }
+        bytes[bit / 8] |= 1 << (bit % 8);
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bitfield_round_trip() {
+        // TM06 (bit 5), TM09 (bit 8), HM01 (bit 50)
+        let slots = vec![5, 8, 50];
+        let bytes = encode_bitfield(&slots, MACHINE_COUNT, TM_COMPAT_SIZE).unwrap();
+        assert_eq!(bytes, vec![0x20, 0x01, 0, 0, 0, 0, 0x04, 0]);
+        assert_eq!(decode_bitfield(&bytes, MACHINE_COUNT), slots);
+        assert_eq!(machine_label(50), "HM01");
+        assert!(encode_bitfield(&[58], MACHINE_COUNT, TM_COMPAT_SIZE).is_err());
+    }
+}
@@ -87,6 +87,69 @@ pub fn decompress_lz77(input: &[u8]) -> Result<Vec<u8>> {
     Ok(output)
 }
 
+/// Longest back-reference the format can encode.
+const MAX_MATCH: usize = 18;
+const MIN_MATCH: usize = 3;
+const WINDOW_SIZE: usize = 0x1000;
+
+/// Compresses data into GBA BIOS LZ77 (Type 0x10) format.
+///
+/// Greedy longest-match search over the 4 KB window. Matches at distance 1 are
+/// never emitted, so the output is also safe for the BIOS VRAM decompressor,
+/// which writes 16 bits at a time. The output is padded to a multiple of 4 bytes.
+pub fn compress_lz77(input: &[u8]) -> Vec<u8> {
+    let mut output = vec![
+        0x10,
+        input.len() as u8,
+        (input.len() >> 8) as u8,
+        (input.len() >> 16) as u8,
+    ];
+    let mut pos = 0;
+
+    while pos < input.len() {
+        let flag_pos = output.len();
+        output.push(0);
+
+        for i in (0..8).rev() {
+            if pos >= input.len() {
+                break;
+            }
+
+            let mut best_len = 0;
+            let mut best_disp = 0;
+            let max_len = MAX_MATCH.min(input.len() - pos);
+            for disp in 2..=WINDOW_SIZE.min(pos) {
+                let len = (0..max_len)
+                    .take_while(|&n| input[pos - disp + n] == input[pos + n])
+                    .count();
+                if len > best_len {
+                    best_len = len;
+                    best_disp = disp;
+                    if len == max_len {
+                        break;
+                    }
+                }
+            }
+
+            if best_len >= MIN_MATCH {
+                output[flag_pos] |= 1 << i;
+                let d = best_disp - 1;
+                output.push((((best_len - MIN_MATCH) << 4) | (d >> 8)) as u8);
+                output.push((d & 0xFF) as u8);
+                pos += best_len;
+            } else {
+                output.push(input[pos]);
+                pos += 1;
+            }
+        }
+    }
+
+    while output.len() % 4 != 0 {
+        output.push(0);
+    }
+    output
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -117,4 +180,14 @@ mod tests {
         let output = decompress_lz77(&input).unwrap();
         assert_eq!(output, vec![0x41, 0x42, 0x43, 0x44]);
     }
+
+    #[test]
+    fn test_lz77_round_trip() {
+        let mut input = b"ABABABABCDCDCDCD".repeat(20);
+        input.extend(0..=255u8);
+        let compressed = compress_lz77(&input);
+        assert!(compressed.len() < input.len());
+        assert_eq!(compressed.len() % 4, 0);
+        assert_eq!(decompress_lz77(&compressed).unwrap(), input);
+    }
 }
@@ -0,0 +1,327 @@
+use crate::patches::differences;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// The kinds of known structure a diff can attribute changes to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StructureKind {
+    BaseStats,
+    Evolutions,
+    Learnset,
+    Move,
+    Item,
+    Trainer,
+    MapHeader,
+    MapLayout,
+    MapObjects,
+    Script,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeStatus {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// One field that differs, e.g. `base_hp` from "45" to "60" or `party[1].level`.
+/// A field missing on one side (a longer list, say) has `None` there.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StructureChange {
+    pub kind: StructureKind,
+    pub index: u32,
+    /// e.g. "Trainer 12 (BROCK)" or "Map 3.1"
+    pub label: String,
+    pub status: ChangeStatus,
+    pub fields: Vec<FieldChange>,
+    /// Changes not broken down by field, e.g. how many map blocks differ
+    pub summary: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ByteRange {
+    pub offset: u32,
+    pub len: u32,
+}
+
+/// Differences between two ROMs, as raw byte ranges and as the known structures they touch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RomDiff {
+    pub before: String,
+    pub after: String,
+    pub changed_bytes: u32,
+    pub changed_ranges: Vec<ByteRange>,
+    pub structures: Vec<StructureChange>,
+}
+
+/// Byte ranges that differ between two ROMs, including bytes only one of them has.
+pub fn changed_ranges(before: &[u8], after: &[u8]) -> Vec<ByteRange> {
+    let mut ranges: Vec<ByteRange> = differences(before, after)
+        .into_iter()
+        .map(|(start, end)| ByteRange {
+            offset: start as u32,
+            len: (end - start) as u32,
+        })
+        .collect();
+    if before.len() > after.len() {
+        ranges.push(ByteRange {
+            offset: after.len() as u32,
+            len: (before.len() - after.len()) as u32,
+        });
+    }
+    ranges
+}
+
+/// Flattens a serialized structure into (path, value) pairs.
+fn flatten(value: &Value, path: String, out: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields {
+                let path = if path.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}.{}", path, name)
+                };
+                flatten(field, path, out);
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                flatten(item, format!("{}[{}]", path, i), out);
+            }
+        }
+        Value::String(text) => out.push((path, text.clone())),
+        other => out.push((path, other.to_string())),
+    }
+}
+
+fn flattened<T: Serialize>(entry: Option<&T>) -> Result<Vec<(String, String)>> {
+    let mut out = Vec::new();
+    if let Some(entry) = entry {
+        flatten(&serde_json::to_value(entry)?, String::new(), &mut out);
+    }
+    Ok(out)
+}
+
+/// The fields that differ between two versions of a structure.
+pub fn field_changes<T: Serialize>(
+    before: Option<&T>,
+    after: Option<&T>,
+) -> Result<Vec<FieldChange>> {
+    let before = flattened(before)?;
+    let after = flattened(after)?;
+    let before_map: HashMap<&str, &str> = before
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    let after_map: HashMap<&str, &str> = after
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+
+    let mut changes = Vec::new();
+    for (field, value) in &before {
+        let other = after_map.get(field.as_str()).copied();
+        if other != Some(value.as_str()) {
+            changes.push(FieldChange {
+                field: field.clone(),
+                before: Some(value.clone()),
+                after: other.map(str::to_string),
+            });
+        }
+    }
+    for (field, value) in &after {
+        if !before_map.contains_key(field.as_str()) {
+            changes.push(FieldChange {
+                field: field.clone(),
+                before: None,
+                after: Some(value.clone()),
+            });
+        }
+    }
+    Ok(changes)
+}
+
+/// Compares two versions of a structure; `None` if it is the same in both ROMs.
+pub fn compare<T: Serialize>(
+    kind: StructureKind,
+    index: u32,
+    label: String,
+    before: Option<&T>,
+    after: Option<&T>,
+) -> Result<Option<StructureChange>> {
+    let status = match (before, after) {
+        (None, None) => return Ok(None),
+        (None, Some(_)) => ChangeStatus::Added,
+        (Some(_), None) => ChangeStatus::Removed,
+        (Some(_), Some(_)) => ChangeStatus::Changed,
+    };
+    let fields = field_changes(before, after)?;
+    if status == ChangeStatus::Changed && fields.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(StructureChange {
+        kind,
+        index,
+        label,
+        status,
+        fields,
+        summary: None,
+    }))
+}
+
+/// Label of a table entry, with its name when it has one: "Move 33 (TACKLE)".
+fn entry_label<T: Serialize>(name: &str, index: u32, entry: Option<&T>) -> String {
+    let entry_name = entry
+        .and_then(|entry| serde_json::to_value(entry).ok())
+        .and_then(|value| value.get("name")?.as_str().map(str::to_string))
+        .filter(|entry_name| !entry_name.is_empty());
+    match entry_name {
+        Some(entry_name) => format!("{} {} ({})", name, index, entry_name),
+        None => format!("{} {}", name, index),
+    }
+}
+
+/// Compares every entry of a table present in either ROM.
+/// The counts may differ when one ROM's table was expanded.
+/// An entry that cannot be read is reported as changed, with the error as its summary,
+/// unless it fails the same way in both ROMs.
+pub fn compare_table<T: Serialize>(
+    kind: StructureKind,
+    name: &str,
+    counts: (u32, u32),
+    read_before: impl Fn(u32) -> Result<T>,
+    read_after: impl Fn(u32) -> Result<T>,
+) -> Result<Vec<StructureChange>> {
+    let mut changes = Vec::new();
+    for index in 0..counts.0.max(counts.1) {
+        let before = (index < counts.0).then(|| read_before(index)).transpose();
+        let after = (index < counts.1).then(|| read_after(index)).transpose();
+        let (before, after) = match (before, after) {
+            (Ok(before), Ok(after)) => (before, after),
+            (before, after) => {
+                let before = before.err().map(|error| error.to_string());
+                let after = after.err().map(|error| error.to_string());
+                if before.is_some() && before == after {
+                    continue;
+                }
+                let summary = [("before", before), ("after", after)]
+                    .into_iter()
+                    .filter_map(|(side, error)| Some(format!("unreadable {}: {}", side, error?)))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                changes.push(StructureChange {
+                    kind,
+                    index,
+                    label: format!("{} {}", name, index),
+                    status: ChangeStatus::Changed,
+                    fields: Vec::new(),
+                    summary: Some(summary),
+                });
+                continue;
+            }
+        };
+        let label = entry_label(name, index, after.as_ref().or(before.as_ref()));
+        changes.extend(compare(
+            kind,
+            index,
+            label,
+            before.as_ref(),
+            after.as_ref(),
+        )?);
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Entry {
+        name: String,
+        level: u8,
+        moves: Vec<u16>,
+    }
+
+    #[test]
+    fn test_compare_table() {
+        let before = [(5, vec![1, 2]), (9, vec![3])];
+        let after = [(5, vec![1, 4, 6]), (9, vec![3]), (2, vec![])];
+        let read = |table: &[(u8, Vec<u16>)], i: u32| {
+            let (level, moves) = table[i as usize].clone();
+            Ok(Entry {
+                name: format!("MON{}", i),
+                level,
+                moves,
+            })
+        };
+
+        let changes = compare_table(
+            StructureKind::Trainer,
+            "Trainer",
+            (2, 3),
+            |i| read(&before, i),
+            |i| read(&after, i),
+        )
+        .unwrap();
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].label, "Trainer 0 (MON0)");
+        assert_eq!(changes[0].status, ChangeStatus::Changed);
+        assert_eq!(
+            changes[0].fields,
+            vec![
+                FieldChange {
+                    field: "moves[1]".to_string(),
+                    before: Some("2".to_string()),
+                    after: Some("4".to_string()),
+                },
+                FieldChange {
+                    field: "moves[2]".to_string(),
+                    before: None,
+                    after: Some("6".to_string()),
+                },
+            ]
+        );
+        assert_eq!(changes[1].index, 2);
+        assert_eq!(changes[1].status, ChangeStatus::Added);
+
+        // An unreadable entry is reported and the rest of the table still compared
+        let changes = compare_table(
+            StructureKind::Trainer,
+            "Trainer",
+            (2, 3),
+            |i| read(&before, i),
+            |i| match i {
+                0 => Err(anyhow::anyhow!("bad party pointer")),
+                _ => read(&after, i),
+            },
+        )
+        .unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            changes[0].summary.as_deref(),
+            Some("unreadable after: bad party pointer")
+        );
+        assert_eq!(changes[1].index, 2);
+
+        let ranges = changed_ranges(&[0, 1, 2, 3], &[0, 9, 2]);
+        assert_eq!(
+            ranges,
+            vec![
+                ByteRange { offset: 1, len: 1 },
+                ByteRange { offset: 3, len: 1 }
+            ]
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use crate::structures::{WildHeader, WildInfo, WildMon};
+use anyhow::{bail, Context, Result};
+use binrw::{BinRead, BinWrite};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+pub const WILD_HEADER_SIZE: usize = 20;
+pub const WILD_INFO_SIZE: usize = 8;
+/// Safety limit when walking the header table looking for its terminator.
+const MAX_WILD_HEADERS: usize = 0x400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EncounterKind {
+    Land,
+    Water,
+    RockSmash,
+    Fishing,
+}
+
+impl EncounterKind {
+    pub const ALL: [EncounterKind; 4] = [
+        EncounterKind::Land,
+        EncounterKind::Water,
+        EncounterKind::RockSmash,
+        EncounterKind::Fishing,
+    ];
+
+    /// Number of Pokémon slots in a table of this kind.
+    /// Fishing slots are split by rod: 0-1 Old, 2-4 Good, 5-9 Super.
+    pub fn slot_count(self) -> usize {
+        match self {
+            EncounterKind::Land => 12,
+            EncounterKind::Water => 5,
+            EncounterKind::RockSmash => 5,
+            EncounterKind::Fishing => 10,
+        }
+    }
+
+    pub fn table_ptr(self, header: &WildHeader) -> u32 {
+        match self {
+            EncounterKind::Land => header.land_ptr,
+            EncounterKind::Water => header.water_ptr,
+            EncounterKind::RockSmash => header.rock_smash_ptr,
+            EncounterKind::Fishing => header.fishing_ptr,
+        }
+    }
+
+    pub fn set_table_ptr(self, header: &mut WildHeader, ptr: u32) {
+        match self {
+            EncounterKind::Land => header.land_ptr = ptr,
+            EncounterKind::Water => header.water_ptr = ptr,
+            EncounterKind::RockSmash => header.rock_smash_ptr = ptr,
+            EncounterKind::Fishing => header.fishing_ptr = ptr,
+        }
+    }
+}
+
+/// Encounter rate and Pokémon slots of one encounter method.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncounterTable {
+    pub encounter_rate: u8,
+    pub slots: Vec<WildMon>,
+}
+
+/// All wild encounter data of a map. Methods the map does not use are `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MapEncounters {
+    pub bank: u8,
+    pub map: u8,
+    pub land: Option<EncounterTable>,
+    pub water: Option<EncounterTable>,
+    pub rock_smash: Option<EncounterTable>,
+    pub fishing: Option<EncounterTable>,
+}
+
+impl MapEncounters {
+    pub fn table(&self, kind: EncounterKind) -> Option<&EncounterTable> {
+        match kind {
+            EncounterKind::Land => self.land.as_ref(),
+            EncounterKind::Water => self.water.as_ref(),
+            EncounterKind::RockSmash => self.rock_smash.as_ref(),
+            EncounterKind::Fishing => self.fishing.as_ref(),
+        }
+    }
+}
+
+/// Reads the header table up to (not including) its 0xFF/0xFF terminator.
+pub fn read_wild_headers(data: &[u8], table_offset: usize) -> Result<Vec<WildHeader>> {
+    let mut reader = Cursor::new(data);
+    reader.set_position(table_offset as u64);
+
+    let mut headers = Vec::new();
+    loop {
+        let header = WildHeader::read(&mut reader).context("Failed to read WildHeader")?;
+        if header.bank == 0xFF && header.map == 0xFF {
+            return Ok(headers);
+        }
+        headers.push(header);
+        if headers.len() > MAX_WILD_HEADERS {
+            bail!("Wild header table has no terminator");
+        }
+    }
+}
+
+fn read_table(data: &[u8], ptr: u32, kind: EncounterKind) -> Result<Option<EncounterTable>> {
+    if ptr == 0 {
+        return Ok(None);
+    }
+
+    let mut reader = Cursor::new(data);
+    reader.set_position((ptr & 0x01FFFFFF) as u64);
+    let info = WildInfo::read(&mut reader).context("Failed to read WildInfo")?;
+
+    reader.set_position((info.mons_ptr & 0x01FFFFFF) as u64);
+    let slots = (0..kind.slot_count())
+        .map(|_| WildMon::read(&mut reader).context("Failed to read WildMon"))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Some(EncounterTable {
+        encounter_rate: info.encounter_rate,
+        slots,
+    }))
+}
+
+pub fn read_map_encounters(data: &[u8], header: &WildHeader) -> Result<MapEncounters> {
+    Ok(MapEncounters {
+        bank: header.bank,
+        map: header.map,
+        land: read_table(data, header.land_ptr, EncounterKind::Land)?,
+        water: read_table(data, header.water_ptr, EncounterKind::Water)?,
+        rock_smash: read_table(data, header.rock_smash_ptr, EncounterKind::RockSmash)?,
+        fishing: read_table(data, header.fishing_ptr, EncounterKind::Fishing)?,
+    })
+}
+
+/// Encodes the Pokémon slots of a table, checking it matches its encounter method.
+pub fn encode_slots(table: &EncounterTable, kind: EncounterKind) -> Result<Vec<u8>> {
+    if table.slots.len() != kind.slot_count() {
+        bail!(
+            "{:?} encounters need {} slots, got {}",
+            kind,
+            kind.slot_count(),
+            table.slots.len()
+        );
+    }
+
+    let mut writer = Cursor::new(Vec::new());
+    for slot in &table.slots {
+        if slot.min_level > slot.max_level {
+            bail!(
+                "Slot level range {}-{} is inverted",
+                slot.min_level,
+                slot.max_level
+            );
+        }
+        slot.write_le(&mut writer)?;
+    }
+    Ok(writer.into_inner())
+}
+
+/// Encodes a new WildInfo followed directly by its slots, for writing at `offset`.
+pub fn encode_new_table(
+    table: &EncounterTable,
+    kind: EncounterKind,
+    offset: u32,
+) -> Result<Vec<u8>> {
+    let info = WildInfo {
+        encounter_rate: table.encounter_rate,
+        padding: vec![0; 3],
+        mons_ptr: 0x08000000 | (offset + WILD_INFO_SIZE as u32),
+    };
+    let mut writer = Cursor::new(Vec::new());
+    info.write_le(&mut writer)?;
+    let mut bytes = writer.into_inner();
+    bytes.extend(encode_slots(table, kind)?);
+    Ok(bytes)
+}
+
+pub fn encode_header(header: &WildHeader) -> Result<Vec<u8>> {
+    let mut writer = Cursor::new(Vec::new());
+    header.write_le(&mut writer)?;
+    Ok(writer.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_map_encounters() {
+        let mut rom = vec![0u8; 0x100];
+        // Header table at 0x00: bank 3, map 1 with water only, then terminator
+        rom[0x00] = 3;
+        rom[0x01] = 1;
+        rom[0x08..0x0C].copy_from_slice(&0x08000040u32.to_le_bytes());
+        rom[0x14] = 0xFF;
+        rom[0x15] = 0xFF;
+
+        let table = EncounterTable {
+            encounter_rate: 4,
+            slots: vec![
+                WildMon {
+                    min_level: 5,
+                    max_level: 35,
+                    species: 72,
+                };
+                5
+            ],
+        };
+        let bytes = encode_new_table(&table, EncounterKind::Water, 0x40).unwrap();
+        rom[0x40..0x40 + bytes.len()].copy_from_slice(&bytes);
+
+        let headers = read_wild_headers(&rom, 0).unwrap();
+        assert_eq!(headers.len(), 1);
+        let encounters = read_map_encounters(&rom, &headers[0]).unwrap();
+        assert_eq!(encounters.bank, 3);
+        assert!(encounters.land.is_none());
+        assert_eq!(encounters.water, Some(table));
+    }
+}
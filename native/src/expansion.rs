@@ -0,0 +1,333 @@
+use crate::offsets::GameOffsets;
+use crate::state::RomState;
+use crate::text::EOS;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// A table that can be given more entries than vanilla.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExpandableTable {
+    Species,
+    Moves,
+    Items,
+}
+
+/// One of the tables indexed by an expandable id, listed in `GameOffsets`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TableField {
+    BaseStats,
+    Evolutions,
+    Learnsets,
+    TmCompatibility,
+    TutorCompatibility,
+    RegionalDex,
+    NationalDex,
+    FrontSprites,
+    BackSprites,
+    NormalPalettes,
+    ShinyPalettes,
+    Icons,
+    IconPaletteIndices,
+    Footprints,
+    SpeciesNames,
+    Cries,
+    ReverseCries,
+    Moves,
+    MoveNames,
+    MoveDescriptions,
+    Items,
+}
+
+/// A table moved by an expansion.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelocatedTable {
+    pub field: TableField,
+    pub old_offset: u32,
+    pub new_offset: u32,
+}
+
+impl ExpandableTable {
+    /// Parallel tables indexed by the same id, which must all grow together.
+    pub fn fields(self) -> &'static [TableField] {
+        match self {
+            ExpandableTable::Species => &[
+                TableField::BaseStats,
+                TableField::Evolutions,
+                TableField::Learnsets,
+                TableField::TmCompatibility,
+                TableField::TutorCompatibility,
+                TableField::RegionalDex,
+                TableField::NationalDex,
+                TableField::FrontSprites,
+                TableField::BackSprites,
+                TableField::NormalPalettes,
+                TableField::ShinyPalettes,
+                TableField::Icons,
+                TableField::IconPaletteIndices,
+                TableField::Footprints,
+                TableField::SpeciesNames,
+                TableField::Cries,
+                TableField::ReverseCries,
+            ],
+            ExpandableTable::Moves => &[
+                TableField::Moves,
+                TableField::MoveNames,
+                TableField::MoveDescriptions,
+            ],
+            ExpandableTable::Items => &[TableField::Items],
+        }
+    }
+
+    fn count_mut(self, offsets: &mut GameOffsets) -> &mut u32 {
+        match self {
+            ExpandableTable::Species => &mut offsets.species_count,
+            ExpandableTable::Moves => &mut offsets.move_count,
+            ExpandableTable::Items => &mut offsets.item_count,
+        }
+    }
+
+    pub fn count(self, offsets: &GameOffsets) -> u32 {
+        match self {
+            ExpandableTable::Species => offsets.species_count,
+            ExpandableTable::Moves => offsets.move_count,
+            ExpandableTable::Items => offsets.item_count,
+        }
+    }
+}
+
+impl TableField {
+    fn offset_mut(self, offsets: &mut GameOffsets) -> &mut u32 {
+        match self {
+            TableField::BaseStats => &mut offsets.base_stats,
+            TableField::Evolutions => &mut offsets.evolutions,
+            TableField::Learnsets => &mut offsets.learnsets,
+            TableField::TmCompatibility => &mut offsets.tm_compatibility,
+            TableField::TutorCompatibility => &mut offsets.tutor_compatibility,
+            TableField::RegionalDex => &mut offsets.regional_dex,
+            TableField::NationalDex => &mut offsets.national_dex,
+            TableField::FrontSprites => &mut offsets.front_sprites,
+            TableField::BackSprites => &mut offsets.back_sprites,
+            TableField::NormalPalettes => &mut offsets.normal_palettes,
+            TableField::ShinyPalettes => &mut offsets.shiny_palettes,
+            TableField::Icons => &mut offsets.icons,
+            TableField::IconPaletteIndices => &mut offsets.icon_palette_indices,
+            TableField::Footprints => &mut offsets.footprints,
+            TableField::SpeciesNames => &mut offsets.species_names,
+            TableField::Cries => &mut offsets.cries,
+            TableField::ReverseCries => &mut offsets.reverse_cries,
+            TableField::Moves => &mut offsets.moves,
+            TableField::MoveNames => &mut offsets.move_names,
+            TableField::MoveDescriptions => &mut offsets.move_descriptions,
+            TableField::Items => &mut offsets.items,
+        }
+    }
+
+    pub fn offset(self, offsets: &GameOffsets) -> u32 {
+        match self {
+            TableField::BaseStats => offsets.base_stats,
+            TableField::Evolutions => offsets.evolutions,
+            TableField::Learnsets => offsets.learnsets,
+            TableField::TmCompatibility => offsets.tm_compatibility,
+            TableField::TutorCompatibility => offsets.tutor_compatibility,
+            TableField::RegionalDex => offsets.regional_dex,
+            TableField::NationalDex => offsets.national_dex,
+            TableField::FrontSprites => offsets.front_sprites,
+            TableField::BackSprites => offsets.back_sprites,
+            TableField::NormalPalettes => offsets.normal_palettes,
+            TableField::ShinyPalettes => offsets.shiny_palettes,
+            TableField::Icons => offsets.icons,
+            TableField::IconPaletteIndices => offsets.icon_palette_indices,
+            TableField::Footprints => offsets.footprints,
+            TableField::SpeciesNames => offsets.species_names,
+            TableField::Cries => offsets.cries,
+            TableField::ReverseCries => offsets.reverse_cries,
+            TableField::Moves => offsets.moves,
+            TableField::MoveNames => offsets.move_names,
+            TableField::MoveDescriptions => offsets.move_descriptions,
+            TableField::Items => offsets.items,
+        }
+    }
+
+    pub fn entry_size(self, offsets: &GameOffsets) -> usize {
+        match self {
+            TableField::BaseStats => 28,
+            TableField::Evolutions => 40,
+            TableField::Learnsets => 4,
+            TableField::TmCompatibility => 8,
+            TableField::TutorCompatibility => offsets.tutor_compat_size(),
+            TableField::RegionalDex | TableField::NationalDex => 2,
+            TableField::FrontSprites
+            | TableField::BackSprites
+            | TableField::NormalPalettes
+            | TableField::ShinyPalettes => 8,
+            TableField::Icons | TableField::Footprints => 4,
+            TableField::IconPaletteIndices => 1,
+            TableField::SpeciesNames => 11,
+            TableField::Cries | TableField::ReverseCries => 12,
+            TableField::Moves => 12,
+            TableField::MoveNames => 13,
+            TableField::MoveDescriptions => 4,
+            TableField::Items => 44,
+        }
+    }
+
+    /// Id of the first entry stored; move descriptions and dex numbers have no entry for id 0.
+    pub fn first_index(self) -> u32 {
+        match self {
+            TableField::MoveDescriptions | TableField::RegionalDex | TableField::NationalDex => 1,
+            _ => 0,
+        }
+    }
+
+    /// Entries the table holds before expanding: the ids from `first_index` up to
+    /// `count`, except for the cry tables, which have their own count.
+    fn stored_entries(self, offsets: &GameOffsets, count: u32) -> u32 {
+        match self {
+            TableField::Cries | TableField::ReverseCries => offsets.cry_count,
+            _ => count - self.first_index(),
+        }
+    }
+
+    /// Bytes new entries are filled with.
+    /// Dex numbers start at 0 (not in the Pokédex); move descriptions are repointed
+    /// to an empty string once the table is moved (see `expand_table`); other tables
+    /// copy their first entry, which is the empty species/move/item. New species
+    /// thus show the name, sprites and icon of species 0.
+    fn template(self, table: &[u8], entry_size: usize) -> Vec<u8> {
+        match self {
+            TableField::RegionalDex | TableField::NationalDex => vec![0; entry_size],
+            _ => table[..entry_size.min(table.len())].to_vec(),
+        }
+    }
+}
+
+/// Appends `new_entries` copies of `template` to a table.
+pub fn extend_table(table: &[u8], template: &[u8], new_entries: usize) -> Vec<u8> {
+    let mut bytes = table.to_vec();
+    for _ in 0..new_entries {
+        bytes.extend_from_slice(template);
+    }
+    bytes
+}
+
+/// Grows `table` to `new_count` entries.
+///
+/// Each parallel table is copied to free space with the new entries appended, and
+/// every pointer to its old location is repointed. The old copies are left in place.
+/// New moves get an empty description, stored right after the description pointers.
+/// The new offsets and count only live in the session's offsets, which `save_rom`
+/// writes next to the saved ROM.
+/// Code that compares ids against hard-coded limits, or maps species to cry ids,
+/// is not patched.
+pub fn expand_table(
+    state: &mut RomState,
+    table: ExpandableTable,
+    new_count: u32,
+) -> Result<Vec<RelocatedTable>> {
+    let mut offsets = state.offsets()?.clone();
+    let count = table.count(&offsets);
+    if new_count <= count {
+        bail!(
+            "{:?} table already has {} entries, cannot expand to {}",
+            table,
+            count,
+            new_count
+        );
+    }
+
+    let new_entries = (new_count - count) as usize;
+    let mut relocated = Vec::new();
+    for &field in table.fields() {
+        let old_offset = field.offset(&offsets);
+        if old_offset == 0 {
+            bail!("{:?} table location unknown, cannot expand", field);
+        }
+        let entry_size = field.entry_size(&offsets);
+        let stored = field.stored_entries(&offsets, count) as usize;
+        let start = old_offset as usize;
+        let Some(old) = state.view().get(start..start + stored * entry_size) else {
+            bail!("{:?} table out of bounds", field);
+        };
+
+        let template = field.template(old, entry_size);
+        let mut bytes = extend_table(old, &template, new_entries);
+        if field == TableField::MoveDescriptions {
+            bytes.push(EOS);
+        }
+        let new_offset = state.relocate(old_offset, bytes)?;
+        if field == TableField::MoveDescriptions {
+            let blank = new_offset + ((stored + new_entries) * entry_size) as u32;
+            let pointers = (0x08000000 | blank).to_le_bytes().repeat(new_entries);
+            state.apply_patch(new_offset + (stored * entry_size) as u32, pointers)?;
+        }
+        *field.offset_mut(&mut offsets) = new_offset;
+        relocated.push(RelocatedTable {
+            field,
+            old_offset,
+            new_offset,
+        });
+    }
+
+    *table.count_mut(&mut offsets) = new_count;
+    if table == ExpandableTable::Species {
+        offsets.cry_count += new_count - count;
+    }
+    state.offsets = Some(offsets);
+    Ok(relocated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::offsets::OffsetDatabase;
+    use crate::state::test_state;
+
+    #[test]
+    fn test_expand_items_repoints() {
+        let mut state = test_state(b"BPRE", 0x800000, 0);
+        let items = state.offsets().unwrap().items;
+        let count = state.offsets().unwrap().item_count;
+        state.data[0x100..0x104].copy_from_slice(&(0x08000000 | items).to_le_bytes());
+        state.data[0x720000..].fill(0xFF);
+
+        let relocated = expand_table(&mut state, ExpandableTable::Items, count + 10).unwrap();
+        assert_eq!(relocated.len(), 1);
+        let new_offset = relocated[0].new_offset;
+
+        let data = state.view();
+        assert_eq!(data[0x100..0x104], (0x08000000 | new_offset).to_le_bytes());
+        let offsets = state.offsets().unwrap();
+        assert_eq!(offsets.items, new_offset);
+        assert_eq!(offsets.item_count, count + 10);
+        // New entries are copies of the blank item 0
+        let end = new_offset as usize + (count as usize + 10) * 44;
+        assert!(data[end - 44..end].iter().all(|&b| b == 0));
+        // The expanded offsets survive being saved as an offset file
+        let mut saved = OffsetDatabase::default();
+        saved
+            .load_ini(&offsets.to_ini("BPRE", "Expanded").unwrap())
+            .unwrap();
+        assert_eq!(saved.lookup("BPRE", 0).as_ref(), Some(offsets));
+        assert!(expand_table(&mut state, ExpandableTable::Items, 5).is_err());
+    }
+
+    #[test]
+    fn test_expanded_moves_have_blank_descriptions() {
+        let mut state = test_state(b"BPRE", 0x800000, 0);
+        state.data[0x720000..].fill(0xFF);
+        let count = state.offsets().unwrap().move_count;
+
+        let relocated = expand_table(&mut state, ExpandableTable::Moves, count + 2).unwrap();
+        let descriptions = relocated
+            .iter()
+            .find(|table| table.field == TableField::MoveDescriptions)
+            .unwrap()
+            .new_offset as usize;
+
+        let data = state.view();
+        let last = descriptions + count as usize * 4;
+        let pointer = u32::from_le_bytes(data[last..last + 4].try_into().unwrap());
+        assert_eq!(data[(pointer & 0x1FFFFFF) as usize], EOS);
+        assert!(relocated.iter().all(|table| table.new_offset % 4 == 0));
+    }
+}
@@ -1,4 +1,6 @@
+use anyhow::{Context, Result};
 use image::{Rgba, RgbaImage};
+use std::io::Cursor;
 
 /// Converts a 15-bit GBA Color (BGR555) to 32-bit RGBA.
 /// GBA Format: xBBBBBGGGGGRRRRR (x is unused)
@@ -16,6 +18,14 @@ pub fn bgr555_to_rgba(color: u16) -> [u8; 4] {
     [r8, g8, b8, 255]
 }
 
+/// Converts a 32-bit RGBA colour to BGR555, dropping the low 3 bits of each channel.
+pub fn rgba_to_bgr555(rgba: [u8; 4]) -> u16 {
+    let r = (rgba[0] >> 3) as u16;
+    let g = (rgba[1] >> 3) as u16;
+    let b = (rgba[2] >> 3) as u16;
+    r | (g << 5) | (b << 10)
+}
+
 /// Decodes a 4bpp tile (32 bytes) into an RgbaImage (8x8).
 /// input: 32 bytes of 4bpp data.
 /// palette: 16 colors (RGBA array or similar, here we take &[u16] raw palette).
@@ -30,12 +40,12 @@ pub fn decode_4bpp_tile(input: &[u8], palette: &[u16]) -> RgbaImage {
             // In 4bpp, each byte holds 2 pixels.
             // Byte 0: Pixel 0 (low nibble), Pixel 1 (high nibble)
             // Stored as: [P1 P0] [P3 P2]
-            
+
             // Index in byte array
             let pixel_idx = (y * 8 + x) as usize;
             let byte_idx = pixel_idx / 2;
             let byte = input[byte_idx];
-            
+
             // If x is even (0, 2..), it's low nibble. If odd, high nibble.
             let palette_index = if x % 2 == 0 {
                 byte & 0xF
@@ -56,6 +66,26 @@ pub fn decode_4bpp_tile(input: &[u8], palette: &[u16]) -> RgbaImage {
     image
 }
 
+/// Encodes an 8x8 tile of palette indices (row-major) into 4bpp data.
+pub fn encode_4bpp_tile(indices: &[u8]) -> Vec<u8> {
+    indices
+        .chunks_exact(2)
+        .map(|pair| (pair[0] & 0xF) | ((pair[1] & 0xF) << 4))
+        .collect()
+}
+
+/// Encodes an image as PNG, the format Flutter's Image.memory reads.
+pub fn encode_png(image: RgbaImage) -> Result<Vec<u8>> {
+    let mut png_data = Vec::new();
+    image::DynamicImage::ImageRgba8(image)
+        .write_to(
+            &mut Cursor::new(&mut png_data),
+            image::ImageOutputFormat::Png,
+        )
+        .context("Failed to encode image as PNG")?;
+    Ok(png_data)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -76,5 +106,6 @@ mod tests {
         let white_gba = 0x7FFF;
         let white_rgba = bgr555_to_rgba(white_gba);
         assert_eq!(white_rgba, [255, 255, 255, 255]);
+        assert_eq!(rgba_to_bgr555(white_rgba), white_gba);
     }
 }
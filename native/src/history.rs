@@ -0,0 +1,225 @@
+use crate::modifications::PendingWrite;
+use crate::offsets::GameOffsets;
+use serde::{Deserialize, Serialize};
+
+/// Change-sets kept for undo; older ones are dropped.
+const MAX_HISTORY: usize = 200;
+
+/// One change to the pending bytes of a range, with what was pending there before.
+/// `after` is empty when the change reverted the range.
+#[derive(Debug, Clone)]
+pub struct RecordedChange {
+    pub offset: u32,
+    pub len: u32,
+    pub before: Vec<PendingWrite>,
+    pub after: Vec<PendingWrite>,
+}
+
+/// The changes made by one editing operation, undone and redone as a unit.
+#[derive(Debug, Clone)]
+pub struct ChangeSet {
+    pub description: String,
+    pub changes: Vec<RecordedChange>,
+    pub offsets_before: Option<GameOffsets>,
+    pub offsets_after: Option<GameOffsets>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub description: String,
+    pub change_count: u32,
+}
+
+/// Undo and redo stacks, most recent last.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryInfo {
+    pub undo: Vec<HistoryEntry>,
+    pub redo: Vec<HistoryEntry>,
+}
+
+/// Edit history of a ROM.
+///
+/// Changes are recorded into the open change-set. Change-sets nest: an operation
+/// started while another is open joins it, so a group of operations is undone at once.
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<ChangeSet>,
+    redo: Vec<ChangeSet>,
+    open: Option<ChangeSet>,
+    depth: usize,
+}
+
+fn entry(set: &ChangeSet) -> HistoryEntry {
+    HistoryEntry {
+        description: set.description.clone(),
+        change_count: set.changes.len() as u32,
+    }
+}
+
+impl History {
+    /// Opens a change-set, or joins the one already open.
+    pub fn begin(&mut self, description: &str, offsets: &Option<GameOffsets>) {
+        if self.depth == 0 {
+            self.open = Some(ChangeSet {
+                description: description.to_string(),
+                changes: Vec::new(),
+                offsets_before: offsets.clone(),
+                offsets_after: None,
+            });
+        }
+        self.depth += 1;
+    }
+
+    /// Closes the current level; the outermost level moves the change-set onto the
+    /// undo stack if it changed anything.
+    pub fn end(&mut self, offsets: &Option<GameOffsets>) {
+        if self.depth == 0 {
+            return;
+        }
+        self.depth -= 1;
+        if self.depth > 0 {
+            return;
+        }
+        let Some(mut set) = self.open.take() else {
+            return;
+        };
+        if set.changes.is_empty() && set.offsets_before == *offsets {
+            return;
+        }
+        set.offsets_after = offsets.clone();
+        self.undo.push(set);
+        if self.undo.len() > MAX_HISTORY {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.depth > 0
+    }
+
+    /// Description of the open change-set.
+    pub fn open_description(&self) -> Option<&str> {
+        self.open.as_ref().map(|set| set.description.as_str())
+    }
+
+    pub fn record(&mut self, change: RecordedChange) {
+        if let Some(set) = self.open.as_mut() {
+            set.changes.push(change);
+        }
+    }
+
+    /// Number of changes in the open change-set, to roll back to on failure.
+    pub fn mark(&self) -> usize {
+        self.open.as_ref().map_or(0, |set| set.changes.len())
+    }
+
+    /// Removes the changes recorded after `mark`, most recent first.
+    pub fn take_since(&mut self, mark: usize) -> Vec<RecordedChange> {
+        let mut changes = match self.open.as_mut() {
+            Some(set) if set.changes.len() > mark => set.changes.split_off(mark),
+            _ => Vec::new(),
+        };
+        changes.reverse();
+        changes
+    }
+
+    /// Change-sets that can be undone, oldest first.
+    pub fn undo_sets(&self) -> &[ChangeSet] {
+        &self.undo
+    }
+
+    pub fn pop_undo(&mut self) -> Option<ChangeSet> {
+        self.undo.pop()
+    }
+
+    pub fn push_undo(&mut self, set: ChangeSet) {
+        self.undo.push(set);
+    }
+
+    pub fn pop_redo(&mut self) -> Option<ChangeSet> {
+        self.redo.pop()
+    }
+
+    pub fn push_redo(&mut self, set: ChangeSet) {
+        self.redo.push(set);
+    }
+
+    pub fn info(&self) -> HistoryInfo {
+        HistoryInfo {
+            undo: self.undo.iter().map(entry).collect(),
+            redo: self.redo.iter().map(entry).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::test_state;
+
+    #[test]
+    fn test_undo_redo_grouped_and_failed_edits() {
+        let mut state = test_state(b"BPRE", 0x200, 0);
+        state.apply_patch(0x100, vec![1, 1]).unwrap();
+
+        state.begin_group("Move NPC and repoint");
+        state
+            .transaction("Move NPC", |s| {
+                s.apply_patch(0x100, vec![2])?;
+                Ok(())
+            })
+            .unwrap();
+        state.apply_patch(0x180, vec![3]).unwrap();
+        state.end_group();
+
+        // A failed operation leaves nothing behind
+        let failed: anyhow::Result<()> = state.transaction("Broken edit", |s| {
+            s.apply_patch(0x101, vec![9])?;
+            anyhow::bail!("no space")
+        });
+        assert!(failed.is_err());
+        assert_eq!(state.view()[0x100..0x102], [2, 1]);
+
+        let info = state.history_info();
+        assert_eq!(info.undo.len(), 2);
+        assert_eq!(info.undo[1].description, "Move NPC and repoint");
+
+        assert_eq!(
+            state.undo().unwrap().as_deref(),
+            Some("Move NPC and repoint")
+        );
+        assert_eq!(state.view()[0x100..0x102], [1, 1]);
+        assert_eq!(state.view()[0x180], 0);
+
+        state.redo().unwrap();
+        assert_eq!(state.view()[0x100], 2);
+        assert_eq!(state.view()[0x180], 3);
+
+        state.undo().unwrap();
+        state.undo().unwrap();
+        assert!(state.modifications.is_empty());
+        assert_eq!(state.undo().unwrap(), None);
+    }
+
+    #[test]
+    fn test_undo_keeps_offsets_changed_outside_history() {
+        let mut state = test_state(b"BPRE", 0x200, 0);
+        let items = state.offsets().unwrap().items;
+        state
+            .transaction("Move items", |s| {
+                s.apply_patch(0x100, vec![1])?;
+                s.offsets.as_mut().unwrap().items = 0x1F0;
+                Ok(())
+            })
+            .unwrap();
+        // e.g. switching to a section with the base stats elsewhere
+        state.offsets.as_mut().unwrap().base_stats = 0x1E0;
+
+        state.undo().unwrap();
+        assert_eq!(state.offsets().unwrap().items, items);
+        assert_eq!(state.offsets().unwrap().base_stats, 0x1E0);
+        state.redo().unwrap();
+        assert_eq!(state.offsets().unwrap().items, 0x1F0);
+        assert_eq!(state.offsets().unwrap().base_stats, 0x1E0);
+    }
+}
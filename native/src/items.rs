@@ -0,0 +1,99 @@
+use crate::structures::Item;
+use crate::text::{decode_text, encode_fixed_text, read_text};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+pub const ITEM_SIZE: usize = 44;
+pub const ITEM_NAME_SIZE: usize = 14;
+
+/// An item table entry with its name and description decoded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemInfo {
+    pub name: String,
+    pub price: u16,
+    pub hold_effect: u8,
+    pub hold_effect_param: u8,
+    pub description: String,
+    pub description_ptr: u32,
+    pub importance: u8,
+    pub registrability: u8,
+    pub pocket: u8,
+    pub item_type: u8,
+    pub field_use_func: u32,
+    pub battle_usage: u8,
+    pub battle_use_func: u32,
+    pub secondary_id: u32,
+}
+
+impl ItemInfo {
+    pub fn from_item(data: &[u8], item: &Item) -> Result<Self> {
+        let description = if item.description_ptr == 0 {
+            String::new()
+        } else {
+            read_text(data, (item.description_ptr & 0x01FFFFFF) as usize)?
+        };
+        Ok(Self {
+            name: decode_text(&item.name),
+            price: item.price,
+            hold_effect: item.hold_effect,
+            hold_effect_param: item.hold_effect_param,
+            description,
+            description_ptr: item.description_ptr,
+            importance: item.importance,
+            registrability: item.registrability,
+            pocket: item.pocket,
+            item_type: item.item_type,
+            field_use_func: item.field_use_func,
+            battle_usage: item.battle_usage,
+            battle_use_func: item.battle_use_func,
+            secondary_id: item.secondary_id,
+        })
+    }
+
+    /// Builds the table entry for item `item_id`.
+    pub fn to_item(&self, item_id: u16) -> Result<Item> {
+        Ok(Item {
+            name: encode_fixed_text(&self.name, ITEM_NAME_SIZE)?,
+            item_id,
+            price: self.price,
+            hold_effect: self.hold_effect,
+            hold_effect_param: self.hold_effect_param,
+            description_ptr: self.description_ptr,
+            importance: self.importance,
+            registrability: self.registrability,
+            pocket: self.pocket,
+            item_type: self.item_type,
+            field_use_func: self.field_use_func,
+            battle_usage: self.battle_usage,
+            padding: vec![0; 3],
+            battle_use_func: self.battle_use_func,
+            secondary_id: self.secondary_id,
+        })
+    }
+}
+
+/// Turns an item name into the constant used in scripts, e.g. "POKé BALL" -> "ITEM_POKE_BALL".
+pub fn item_constant(name: &str) -> String {
+    let mut constant = String::from("ITEM_");
+    for c in name.chars() {
+        match c {
+            'é' | 'É' => constant.push('E'),
+            c if c.is_ascii_alphanumeric() => constant.push(c.to_ascii_uppercase()),
+            _ if !constant.ends_with('_') => constant.push('_'),
+            _ => {}
+        }
+    }
+    constant.trim_end_matches('_').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_item_constant() {
+        assert_eq!(item_constant("POKé BALL"), "ITEM_POKE_BALL");
+        assert_eq!(item_constant("X SP. ATK"), "ITEM_X_SP_ATK");
+        assert_eq!(item_constant("POTION"), "ITEM_POTION");
+    }
+}
@@ -1,12 +1,37 @@
 mod frb_generated; /* AUTO INJECTED BY flutter_rust_bridge. This line may not be accurate, and you can change it according to your needs. */
 
 pub mod api;
+pub mod checksum;
+pub mod compatibility;
 pub mod compression;
+pub mod diff;
+pub mod encounters;
+pub mod expansion;
 pub mod graphics;
+pub mod history;
+pub mod items;
+pub mod map_renderer;
+pub mod maps;
+pub mod metatiles;
+pub mod modifications;
+pub mod moves;
+pub mod offsets;
+pub mod overworld;
+pub mod patches;
+pub mod pokedex;
+pub mod profile;
+pub mod project;
+pub mod rom_view;
 pub mod scripting;
 pub mod space_manager;
+pub mod species;
+pub mod sprites;
 pub mod state;
 pub mod structures;
+pub mod tables;
+pub mod text;
+pub mod tileset_anim;
+pub mod trainers;
 
 use flutter_rust_bridge::frb;
 
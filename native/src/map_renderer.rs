@@ -0,0 +1,243 @@
+use crate::api::read_tileset_header;
+use crate::compression::decompress_lz77;
+use crate::graphics::{bgr555_to_rgba, decode_4bpp_tile};
+use crate::maps::MapBlockGrid;
+use crate::metatiles::{read_metatile, AttributeFormat, Metatile, MetatileTile};
+use crate::structures::{MapLayout, TilesetHeader};
+use crate::tileset_anim::TileAnimation;
+use anyhow::{bail, Result};
+use image::{Rgba, RgbaImage};
+use std::collections::HashMap;
+
+/// Tiles addressable by a map: primary tiles first, then the secondary tileset's.
+const TOTAL_TILES: usize = 1024;
+/// Palettes shared by the two tilesets of a map.
+const TOTAL_PALETTES: usize = 13;
+
+/// Everything needed to draw the blocks of one layout.
+pub struct MapTilesets {
+    /// 4bpp tile data, 32 bytes per tile.
+    pub tiles: Vec<u8>,
+    /// 16 colours per palette.
+    pub palettes: Vec<u16>,
+    /// Metatile ids below this come from the primary tileset.
+    pub split: usize,
+    pub primary_metatiles: Vec<Metatile>,
+    pub secondary_metatiles: Vec<Metatile>,
+    pub primary: TilesetHeader,
+    pub secondary: TilesetHeader,
+}
+
+impl MapTilesets {
+    pub fn metatile(&self, id: u16) -> Option<&Metatile> {
+        let id = id as usize;
+        if id < self.split {
+            self.primary_metatiles.get(id)
+        } else {
+            self.secondary_metatiles.get(id - self.split)
+        }
+    }
+}
+
+fn read_tileset_tiles(data: &[u8], tileset: &TilesetHeader, max_tiles: usize) -> Result<Vec<u8>> {
+    let offset = (tileset.graphics_ptr & 0x01FFFFFF) as usize;
+    if offset >= data.len() {
+        bail!("Graphics ptr out of bounds");
+    }
+    let mut gfx = if tileset.is_compressed == 1 {
+        decompress_lz77(&data[offset..])?
+    } else {
+        let end = (offset + max_tiles * 32).min(data.len());
+        data[offset..end].to_vec()
+    };
+    gfx.truncate(max_tiles * 32);
+    Ok(gfx)
+}
+
+fn read_palettes(data: &[u8], tileset: &TilesetHeader, range: std::ops::Range<usize>) -> Vec<u16> {
+    let offset = (tileset.palette_ptr & 0x01FFFFFF) as usize;
+    range
+        .flat_map(|pal| (0..16).map(move |c| offset + (pal * 16 + c) * 2))
+        .map(|o| {
+            data.get(o..o + 2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .unwrap_or(0)
+        })
+        .collect()
+}
+
+fn read_tileset_metatiles(
+    data: &[u8],
+    tileset: &TilesetHeader,
+    format: AttributeFormat,
+) -> Vec<Metatile> {
+    let metatiles = (tileset.metatiles_ptr & 0x01FFFFFF) as usize;
+    let behavior = (tileset.attributes_ptr(format) & 0x01FFFFFF) as usize;
+    (0..format.metatile_count(tileset.is_secondary != 0))
+        .map_while(|i| read_metatile(data, metatiles, behavior, i, format).ok())
+        .collect()
+}
+
+/// Loads the tiles, palettes and metatiles of a layout's two tilesets.
+/// The primary tileset supplies the low tiles, palettes and metatiles; the secondary the rest.
+pub fn load_map_tilesets(
+    data: &[u8],
+    layout: &MapLayout,
+    format: AttributeFormat,
+) -> Result<MapTilesets> {
+    let primary = read_tileset_header(data, layout.primary_tileset_ptr)?;
+    let secondary = read_tileset_header(data, layout.secondary_tileset_ptr)?;
+
+    // Primary tiles and metatiles share the same split point
+    let split = format.metatile_count(false);
+    let primary_palettes = match format {
+        AttributeFormat::FireRed => 7,
+        AttributeFormat::Emerald => 6,
+    };
+
+    let mut tiles = read_tileset_tiles(data, &primary, split)?;
+    tiles.resize(split * 32, 0);
+    tiles.extend(read_tileset_tiles(data, &secondary, TOTAL_TILES - split)?);
+    tiles.resize(TOTAL_TILES * 32, 0);
+
+    let mut palettes = read_palettes(data, &primary, 0..primary_palettes);
+    palettes.extend(read_palettes(
+        data,
+        &secondary,
+        primary_palettes..TOTAL_PALETTES,
+    ));
+
+    Ok(MapTilesets {
+        tiles,
+        palettes,
+        split,
+        primary_metatiles: read_tileset_metatiles(data, &primary, format),
+        secondary_metatiles: read_tileset_metatiles(data, &secondary, format),
+        primary,
+        secondary,
+    })
+}
+
+fn draw_tile(
+    image: &mut RgbaImage,
+    x: u32,
+    y: u32,
+    entry: &MetatileTile,
+    tilesets: &MapTilesets,
+    overrides: &HashMap<u16, Vec<u8>>,
+) {
+    let index = entry.tile_index as usize;
+    let tile_data = match overrides.get(&entry.tile_index) {
+        Some(tile) => tile.as_slice(),
+        None if (index + 1) * 32 <= tilesets.tiles.len() => {
+            &tilesets.tiles[index * 32..(index + 1) * 32]
+        }
+        None => return,
+    };
+    let pal_start = entry.palette as usize * 16;
+    if pal_start + 16 > tilesets.palettes.len() {
+        return;
+    }
+
+    let tile = decode_4bpp_tile(tile_data, &tilesets.palettes[pal_start..pal_start + 16]);
+    for ty in 0..8 {
+        for tx in 0..8 {
+            let sx = if entry.hflip { 7 - tx } else { tx };
+            let sy = if entry.vflip { 7 - ty } else { ty };
+            let pixel = tile.get_pixel(sx, sy);
+            // Colour 0 is transparent and leaves the layer below visible
+            if pixel[3] != 0 {
+                image.put_pixel(x + tx, y + ty, *pixel);
+            }
+        }
+    }
+}
+
+/// Draws a block grid, 16x16 pixels per block.
+/// `overrides` replaces individual tiles, which is how animation frames are shown.
+pub fn render_blocks(
+    grid: &MapBlockGrid,
+    tilesets: &MapTilesets,
+    overrides: &HashMap<u16, Vec<u8>>,
+) -> RgbaImage {
+    let backdrop = bgr555_to_rgba(tilesets.palettes.first().copied().unwrap_or(0));
+    let mut image = RgbaImage::from_pixel(grid.width * 16, grid.height * 16, Rgba(backdrop));
+
+    for (i, block) in grid.blocks.iter().enumerate() {
+        let Some(metatile) = tilesets.metatile(block.metatile) else {
+            continue;
+        };
+        let bx = (i as u32 % grid.width) * 16;
+        let by = (i as u32 / grid.width) * 16;
+
+        for layer in [&metatile.bottom, &metatile.top] {
+            for (n, entry) in layer.iter().enumerate() {
+                let x = bx + (n as u32 % 2) * 8;
+                let y = by + (n as u32 / 2) * 8;
+                draw_tile(&mut image, x, y, entry, tilesets, overrides);
+            }
+        }
+    }
+
+    image
+}
+
+/// Builds the tile overrides showing each animation's frame at `tick`.
+pub fn animation_overrides(
+    data: &[u8],
+    animations: &[TileAnimation],
+    tick: u32,
+) -> HashMap<u16, Vec<u8>> {
+    let mut overrides = HashMap::new();
+    for animation in animations {
+        let start = (animation.frame_at(tick) & 0x01FFFFFF) as usize;
+        for n in 0..animation.tile_count as usize {
+            let offset = start + n * 32;
+            if let Some(tile) = data.get(offset..offset + 32) {
+                overrides.insert(animation.dest_tile + n as u16, tile.to_vec());
+            }
+        }
+    }
+    overrides
+}
+
+/// Draws an overworld sprite standing on block (`x`, `y`): centred horizontally on
+/// the block with its feet on the block's bottom edge, as the game places objects.
+pub fn draw_object(image: &mut RgbaImage, sprite: &RgbaImage, x: i32, y: i32) {
+    let left = x * 16 + 8 - sprite.width() as i32 / 2;
+    let top = y * 16 + 16 - sprite.height() as i32;
+    for (sx, sy, pixel) in sprite.enumerate_pixels() {
+        let (px, py) = (left + sx as i32, top + sy as i32);
+        if pixel[3] != 0
+            && px >= 0
+            && py >= 0
+            && (px as u32) < image.width()
+            && (py as u32) < image.height()
+        {
+            image.put_pixel(px as u32, py as u32, *pixel);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emerald_tileset_attributes_come_before_callback() {
+        let mut data = vec![0u8; 0x400];
+        // Emerald layout: attributes at 0x10, callback at 0x14
+        data[0] = 0; // primary
+        data[0x0C..0x10].copy_from_slice(&0x0800_0100u32.to_le_bytes());
+        data[0x10..0x14].copy_from_slice(&0x0800_0300u32.to_le_bytes());
+        data[0x14..0x18].copy_from_slice(&0x0800_0381u32.to_le_bytes());
+        // Metatile 0 has behaviour 0x2A, layer 1
+        data[0x300..0x302].copy_from_slice(&0x102Au16.to_le_bytes());
+
+        let tileset = read_tileset_header(&data, 0x0800_0000).unwrap();
+        assert_eq!(tileset.callback_ptr(AttributeFormat::Emerald), 0x0800_0381);
+        let metatiles = read_tileset_metatiles(&data, &tileset, AttributeFormat::Emerald);
+        assert_eq!(metatiles[0].attributes.behavior, 0x2A);
+        assert_eq!(metatiles[0].attributes.layer_type, 1);
+    }
+}
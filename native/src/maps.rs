@@ -0,0 +1,341 @@
+use crate::structures::MapLayout;
+use crate::tables::read_entry;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// A single entry of a map's block grid.
+/// Stored in ROM as a u16: `EEEE CCMM MMMM MMMM`
+/// - M: Metatile id (10 bits)
+/// - C: Collision (2 bits)
+/// - E: Elevation (4 bits)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MapBlock {
+    pub metatile: u16,
+    pub collision: u8,
+    pub elevation: u8,
+}
+
+impl MapBlock {
+    pub fn from_raw(raw: u16) -> Self {
+        Self {
+            metatile: raw & 0x3FF,
+            collision: ((raw >> 10) & 0x3) as u8,
+            elevation: ((raw >> 12) & 0xF) as u8,
+        }
+    }
+
+    pub fn to_raw(&self) -> u16 {
+        (self.metatile & 0x3FF)
+            | (((self.collision & 0x3) as u16) << 10)
+            | (((self.elevation & 0xF) as u16) << 12)
+    }
+}
+
+/// A rectangular region of blocks, stored row by row.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapBlockGrid {
+    pub width: u32,
+    pub height: u32,
+    pub blocks: Vec<MapBlock>,
+}
+
+/// Number of blocks in a `width` x `height` grid.
+pub fn block_count(width: u32, height: u32) -> Result<usize> {
+    match width.checked_mul(height) {
+        Some(count) => Ok(count as usize),
+        None => bail!("Map size {}x{} is too large", width, height),
+    }
+}
+
+/// Reads the whole block grid of a layout.
+pub fn read_blocks(data: &[u8], layout: &MapLayout) -> Result<MapBlockGrid> {
+    let start = (layout.map_data_ptr & 0x01FFFFFF) as usize;
+    let count = block_count(layout.width, layout.height)?;
+    let end = count.checked_mul(2).and_then(|len| len.checked_add(start));
+    let Some(end) = end.filter(|&end| end <= data.len()) else {
+        bail!("Map block data out of bounds");
+    };
+
+    let blocks = data[start..end]
+        .chunks_exact(2)
+        .map(|b| MapBlock::from_raw(u16::from_le_bytes([b[0], b[1]])))
+        .collect();
+
+    Ok(MapBlockGrid {
+        width: layout.width,
+        height: layout.height,
+        blocks,
+    })
+}
+
+/// Builds the patches that write `region` into the layout with its top-left corner at (x, y).
+/// Parts of the region that fall outside the map are clipped.
+/// Each row becomes one patch, since rows are the only contiguous runs in the grid.
+pub fn region_patches(
+    layout: &MapLayout,
+    x: u32,
+    y: u32,
+    region: &MapBlockGrid,
+) -> Result<Vec<(u32, Vec<u8>)>> {
+    if region.blocks.len() != block_count(region.width, region.height)? {
+        bail!(
+            "Block region is {}x{} but holds {} blocks",
+            region.width,
+            region.height,
+            region.blocks.len()
+        );
+    }
+    if x >= layout.width || y >= layout.height {
+        bail!(
+            "Block ({}, {}) is outside the {}x{} map",
+            x,
+            y,
+            layout.width,
+            layout.height
+        );
+    }
+
+    let base = (layout.map_data_ptr & 0x01FFFFFF) as u64;
+    let cols = region.width.min(layout.width - x);
+    let rows = region.height.min(layout.height - y);
+
+    let mut patches = Vec::with_capacity(rows as usize);
+    for row in 0..rows {
+        let src = (row * region.width) as usize;
+        let bytes = region.blocks[src..src + cols as usize]
+            .iter()
+            .flat_map(|b| b.to_raw().to_le_bytes())
+            .collect();
+        let offset = base + ((y + row) as u64 * layout.width as u64 + x as u64) * 2;
+        let Ok(offset) = u32::try_from(offset) else {
+            bail!("Map block data out of bounds");
+        };
+        patches.push((offset, bytes));
+    }
+
+    Ok(patches)
+}
+
+/// Which edge or corner of the old grid stays in place when a map is resized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResizeAnchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl ResizeAnchor {
+    /// Horizontal and vertical alignment as 0 (start), 1 (middle) or 2 (end).
+    fn alignment(self) -> (i64, i64) {
+        match self {
+            ResizeAnchor::TopLeft => (0, 0),
+            ResizeAnchor::Top => (1, 0),
+            ResizeAnchor::TopRight => (2, 0),
+            ResizeAnchor::Left => (0, 1),
+            ResizeAnchor::Center => (1, 1),
+            ResizeAnchor::Right => (2, 1),
+            ResizeAnchor::BottomLeft => (0, 2),
+            ResizeAnchor::Bottom => (1, 2),
+            ResizeAnchor::BottomRight => (2, 2),
+        }
+    }
+}
+
+/// The game reserves 0x2800 blocks for the map plus its border margin
+/// (7 blocks left/right, 7 blocks up/down). Larger maps overflow that buffer.
+pub const MAX_MAP_BLOCKS: u32 = 0x2800;
+
+pub fn validate_map_size(width: u32, height: u32) -> Result<()> {
+    if width == 0 || height == 0 {
+        bail!("Map size must be at least 1x1");
+    }
+    let fits = (width as u64 + 15)
+        .checked_mul(height as u64 + 14)
+        .is_some_and(|blocks| blocks <= MAX_MAP_BLOCKS as u64);
+    if !fits {
+        bail!(
+            "Map size {}x{} exceeds the game's map buffer",
+            width,
+            height
+        );
+    }
+    Ok(())
+}
+
+/// Builds a `new_width` x `new_height` grid from `grid`.
+/// The old blocks are kept at the position given by `anchor`; new space is filled with `fill`.
+pub fn resize_grid(
+    grid: &MapBlockGrid,
+    new_width: u32,
+    new_height: u32,
+    anchor: ResizeAnchor,
+    fill: MapBlock,
+) -> MapBlockGrid {
+    let (ax, ay) = anchor.alignment();
+    // Offset of the old grid inside the new one (negative when shrinking)
+    let dx = (new_width as i64 - grid.width as i64) * ax / 2;
+    let dy = (new_height as i64 - grid.height as i64) * ay / 2;
+
+    let mut blocks = vec![fill; (new_width * new_height) as usize];
+    for y in 0..new_height as i64 {
+        let old_y = y - dy;
+        if old_y < 0 || old_y >= grid.height as i64 {
+            continue;
+        }
+        for x in 0..new_width as i64 {
+            let old_x = x - dx;
+            if old_x < 0 || old_x >= grid.width as i64 {
+                continue;
+            }
+            blocks[(y * new_width as i64 + x) as usize] =
+                grid.blocks[(old_y * grid.width as i64 + old_x) as usize];
+        }
+    }
+
+    MapBlockGrid {
+        width: new_width,
+        height: new_height,
+        blocks,
+    }
+}
+
+pub fn encode_blocks(grid: &MapBlockGrid) -> Vec<u8> {
+    grid.blocks
+        .iter()
+        .flat_map(|b| b.to_raw().to_le_bytes())
+        .collect()
+}
+
+/// Upper bound on maps per bank, in case a bank's list runs into unrelated data.
+const MAX_MAPS_PER_BANK: u32 = 256;
+
+/// Reads the map bank table: for each bank, the pointers to its map headers.
+/// The table does not store bank sizes; a bank's list ends where the next list
+/// (or the table itself) starts, or at the first entry that is not a ROM pointer.
+pub fn read_map_banks(data: &[u8], table_offset: u32, bank_count: u32) -> Result<Vec<Vec<u32>>> {
+    let starts = (0..bank_count)
+        .map(|bank| {
+            let ptr: u32 = read_entry(data, table_offset + bank * 4, 4)?;
+            Ok(ptr & 0x01FFFFFF)
+        })
+        .collect::<Result<Vec<u32>>>()?;
+
+    Ok(starts
+        .iter()
+        .map(|&start| {
+            let end = starts
+                .iter()
+                .copied()
+                .chain([table_offset])
+                .filter(|&next| next > start)
+                .min()
+                .unwrap_or(u32::MAX);
+            let count = ((end - start) / 4).min(MAX_MAPS_PER_BANK);
+            (0..count)
+                .map_while(|i| read_entry::<u32>(data, start + i * 4, 4).ok())
+                .take_while(|ptr| (0x08000000..0x0A000000).contains(ptr))
+                .collect()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(width: u32, height: u32) -> MapLayout {
+        MapLayout {
+            width,
+            height,
+            border_ptr: 0,
+            map_data_ptr: 0x08000100,
+            primary_tileset_ptr: 0,
+            secondary_tileset_ptr: 0,
+            border_width: 2,
+            border_height: 2,
+            unused: vec![0, 0],
+        }
+    }
+
+    #[test]
+    fn test_read_map_banks() {
+        // Bank 0 has two maps, bank 1 one map followed by the bank table at 0x20
+        let mut data = vec![0u8; 0x28];
+        for (i, ptr) in [0x08000100u32, 0x08000200, 0x08000300].iter().enumerate() {
+            data[0x10 + i * 4..0x14 + i * 4].copy_from_slice(&ptr.to_le_bytes());
+        }
+        data[0x20..0x24].copy_from_slice(&0x08000010u32.to_le_bytes());
+        data[0x24..0x28].copy_from_slice(&0x08000018u32.to_le_bytes());
+
+        let banks = read_map_banks(&data, 0x20, 2).unwrap();
+        assert_eq!(banks, vec![vec![0x08000100, 0x08000200], vec![0x08000300]]);
+    }
+
+    #[test]
+    fn test_block_raw_round_trip() {
+        // Metatile 0x2A5, collision 1, elevation 3 -> 0011 01 10 1010 0101
+        let block = MapBlock::from_raw(0x36A5);
+        assert_eq!(block.metatile, 0x2A5);
+        assert_eq!(block.collision, 1);
+        assert_eq!(block.elevation, 3);
+        assert_eq!(block.to_raw(), 0x36A5);
+    }
+
+    #[test]
+    fn test_region_patches_clip_to_map() {
+        // 2x2 region pasted at (3, 1) on a 4x2 map: only the left column and first row fit.
+        let block = MapBlock::from_raw(0x0001);
+        let region = MapBlockGrid {
+            width: 2,
+            height: 2,
+            blocks: vec![block; 4],
+        };
+        let patches = region_patches(&layout(4, 2), 3, 1, &region).unwrap();
+
+        assert_eq!(patches.len(), 1);
+        assert_eq!(patches[0].0, 0x100 + (4 + 3) * 2);
+        assert_eq!(patches[0].1, vec![0x01, 0x00]);
+    }
+
+    #[test]
+    fn test_read_blocks_rejects_overflowing_layout() {
+        let data = vec![0u8; 0x200];
+        assert!(read_blocks(&data, &layout(0x10000, 0x10000)).is_err());
+        assert_eq!(read_blocks(&data, &layout(4, 2)).unwrap().blocks.len(), 8);
+    }
+
+    #[test]
+    fn test_resize_grid_anchor() {
+        let old = MapBlockGrid {
+            width: 2,
+            height: 1,
+            blocks: vec![MapBlock::from_raw(1), MapBlock::from_raw(2)],
+        };
+        let fill = MapBlock::from_raw(9);
+
+        // Growing to 4x1 anchored right shifts the old blocks to the end
+        let grown = resize_grid(&old, 4, 1, ResizeAnchor::Right, fill);
+        let raw: Vec<u16> = grown.blocks.iter().map(|b| b.to_raw()).collect();
+        assert_eq!(raw, vec![9, 9, 1, 2]);
+
+        // Shrinking to 1x2 anchored bottom-right keeps the last column
+        let shrunk = resize_grid(&old, 1, 2, ResizeAnchor::BottomRight, fill);
+        let raw: Vec<u16> = shrunk.blocks.iter().map(|b| b.to_raw()).collect();
+        assert_eq!(raw, vec![9, 2]);
+    }
+
+    #[test]
+    fn test_validate_map_size_rejects_overflowing_sizes() {
+        assert!(validate_map_size(20, 20).is_ok());
+        assert!(validate_map_size(0, 20).is_err());
+        // (0xFFF1 + 15) * (0xFFF2 + 14) wraps to 0 in u32
+        assert!(validate_map_size(0xFFF1, 0xFFF2).is_err());
+        assert!(validate_map_size(u32::MAX, u32::MAX).is_err());
+    }
+}
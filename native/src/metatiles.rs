@@ -0,0 +1,219 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// Size of one metatile definition: 8 tile entries of 2 bytes.
+pub const METATILE_SIZE: usize = 16;
+
+/// How metatile behaviour/background attributes are stored.
+/// FireRed/LeafGreen use a u32 per metatile, Ruby/Sapphire/Emerald a u16.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AttributeFormat {
+    FireRed,
+    Emerald,
+}
+
+impl AttributeFormat {
+    pub fn from_game_code(game_code: &str) -> Self {
+        match game_code {
+            "BPRE" | "BPGE" => AttributeFormat::FireRed,
+            _ => AttributeFormat::Emerald,
+        }
+    }
+
+    pub fn size(self) -> usize {
+        match self {
+            AttributeFormat::FireRed => 4,
+            AttributeFormat::Emerald => 2,
+        }
+    }
+
+    /// Number of metatiles a primary or secondary tileset holds.
+    pub fn metatile_count(self, is_secondary: bool) -> usize {
+        match (self, is_secondary) {
+            (AttributeFormat::FireRed, false) => 640,
+            (AttributeFormat::FireRed, true) => 384,
+            (AttributeFormat::Emerald, _) => 512,
+        }
+    }
+}
+
+/// One 8x8 tile of a metatile.
+/// Stored in ROM as a u16: `PPPP VHTT TTTT TTTT`
+/// - T: Tile index (10 bits)
+/// - H/V: Horizontal/Vertical flip
+/// - P: Palette (4 bits)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetatileTile {
+    pub tile_index: u16,
+    pub palette: u8,
+    pub hflip: bool,
+    pub vflip: bool,
+}
+
+impl MetatileTile {
+    pub fn from_raw(raw: u16) -> Self {
+        Self {
+            tile_index: raw & 0x3FF,
+            hflip: raw & 0x400 != 0,
+            vflip: raw & 0x800 != 0,
+            palette: ((raw >> 12) & 0xF) as u8,
+        }
+    }
+
+    pub fn to_raw(&self) -> u16 {
+        (self.tile_index & 0x3FF)
+            | ((self.hflip as u16) << 10)
+            | ((self.vflip as u16) << 11)
+            | (((self.palette & 0xF) as u16) << 12)
+    }
+}
+
+/// Behaviour and background attributes of a metatile.
+/// `terrain_type` and `encounter_type` only exist in the FireRed format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetatileAttributes {
+    pub behavior: u16,
+    pub terrain_type: u8,
+    pub encounter_type: u8,
+    pub layer_type: u8,
+}
+
+impl MetatileAttributes {
+    /// FireRed: `0LL0 0EEE 0000 0000 00TT TTTB BBBB BBBB`
+    /// Emerald: `LLLL 0000 BBBB BBBB`
+    pub fn from_raw(raw: u32, format: AttributeFormat) -> Self {
+        match format {
+            AttributeFormat::FireRed => Self {
+                behavior: (raw & 0x1FF) as u16,
+                terrain_type: ((raw >> 9) & 0x1F) as u8,
+                encounter_type: ((raw >> 24) & 0x7) as u8,
+                layer_type: ((raw >> 29) & 0x3) as u8,
+            },
+            AttributeFormat::Emerald => Self {
+                behavior: (raw & 0xFF) as u16,
+                terrain_type: 0,
+                encounter_type: 0,
+                layer_type: ((raw >> 12) & 0xF) as u8,
+            },
+        }
+    }
+
+    pub fn to_raw(&self, format: AttributeFormat) -> u32 {
+        match format {
+            AttributeFormat::FireRed => {
+                (self.behavior as u32 & 0x1FF)
+                    | ((self.terrain_type as u32 & 0x1F) << 9)
+                    | ((self.encounter_type as u32 & 0x7) << 24)
+                    | ((self.layer_type as u32 & 0x3) << 29)
+            }
+            AttributeFormat::Emerald => {
+                (self.behavior as u32 & 0xFF) | ((self.layer_type as u32 & 0xF) << 12)
+            }
+        }
+    }
+
+    pub fn to_bytes(&self, format: AttributeFormat) -> Vec<u8> {
+        let raw = self.to_raw(format);
+        raw.to_le_bytes()[..format.size()].to_vec()
+    }
+}
+
+/// A 16x16 metatile: four tiles drawn below the player and four drawn above.
+/// Tiles are ordered top-left, top-right, bottom-left, bottom-right.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metatile {
+    pub bottom: Vec<MetatileTile>,
+    pub top: Vec<MetatileTile>,
+    pub attributes: MetatileAttributes,
+}
+
+impl Metatile {
+    /// Decodes a metatile from its 16 definition bytes and its attribute bytes.
+    pub fn decode(tiles: &[u8], attributes: &[u8], format: AttributeFormat) -> Result<Self> {
+        if tiles.len() < METATILE_SIZE || attributes.len() < format.size() {
+            bail!("Metatile data too short");
+        }
+
+        let entries: Vec<MetatileTile> = tiles[..METATILE_SIZE]
+            .chunks_exact(2)
+            .map(|b| MetatileTile::from_raw(u16::from_le_bytes([b[0], b[1]])))
+            .collect();
+
+        let mut raw_attr = [0u8; 4];
+        raw_attr[..format.size()].copy_from_slice(&attributes[..format.size()]);
+
+        Ok(Self {
+            bottom: entries[..4].to_vec(),
+            top: entries[4..].to_vec(),
+            attributes: MetatileAttributes::from_raw(u32::from_le_bytes(raw_attr), format),
+        })
+    }
+
+    /// Encodes the 16 definition bytes.
+    pub fn encode_tiles(&self) -> Result<Vec<u8>> {
+        if self.bottom.len() != 4 || self.top.len() != 4 {
+            bail!("A metatile needs exactly 4 bottom and 4 top tiles");
+        }
+        Ok(self
+            .bottom
+            .iter()
+            .chain(self.top.iter())
+            .flat_map(|t| t.to_raw().to_le_bytes())
+            .collect())
+    }
+}
+
+/// Reads metatile `index` of a tileset whose definitions start at `metatiles_offset`
+/// and attributes at `behavior_offset`.
+pub fn read_metatile(
+    data: &[u8],
+    metatiles_offset: usize,
+    behavior_offset: usize,
+    index: usize,
+    format: AttributeFormat,
+) -> Result<Metatile> {
+    let tile_start = metatiles_offset + index * METATILE_SIZE;
+    let attr_start = behavior_offset + index * format.size();
+    if tile_start + METATILE_SIZE > data.len() || attr_start + format.size() > data.len() {
+        bail!("Metatile {} out of bounds", index);
+    }
+    Metatile::decode(
+        &data[tile_start..tile_start + METATILE_SIZE],
+        &data[attr_start..attr_start + format.size()],
+        format,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metatile_tile_raw_round_trip() {
+        // Palette 5, vflip, tile 0x123 -> 0101 1001 0010 0011
+        let tile = MetatileTile::from_raw(0x5923);
+        assert_eq!(tile.tile_index, 0x123);
+        assert_eq!(tile.palette, 5);
+        assert!(tile.vflip);
+        assert!(!tile.hflip);
+        assert_eq!(tile.to_raw(), 0x5923);
+    }
+
+    #[test]
+    fn test_attributes_both_formats() {
+        // FireRed: layer 1, encounter 1 (land), terrain 2, behavior 0x2A
+        let raw = (1 << 29) | (1 << 24) | (2 << 9) | 0x2A;
+        let attr = MetatileAttributes::from_raw(raw, AttributeFormat::FireRed);
+        assert_eq!(attr.behavior, 0x2A);
+        assert_eq!(attr.terrain_type, 2);
+        assert_eq!(attr.encounter_type, 1);
+        assert_eq!(attr.layer_type, 1);
+        assert_eq!(attr.to_bytes(AttributeFormat::FireRed), raw.to_le_bytes());
+
+        // Emerald: layer 1, behavior 0x2A
+        let attr = MetatileAttributes::from_raw(0x102A, AttributeFormat::Emerald);
+        assert_eq!(attr.behavior, 0x2A);
+        assert_eq!(attr.layer_type, 1);
+        assert_eq!(attr.to_bytes(AttributeFormat::Emerald), vec![0x2A, 0x10]);
+    }
+}
@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Size of the cartridge address space; no write may end past it.
+pub const MAX_ROM_SIZE: u32 = 0x2000000;
+
+/// A run of pending bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingWrite {
+    pub offset: u32,
+    pub bytes: Vec<u8>,
+}
+
+impl PendingWrite {
+    pub fn end(&self) -> u32 {
+        self.offset + self.bytes.len() as u32
+    }
+}
+
+/// Free space claimed for new or moved data, and the edit that claimed it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Allocation {
+    pub offset: u32,
+    pub size: u32,
+    pub description: String,
+}
+
+impl Allocation {
+    pub fn end(&self) -> u32 {
+        self.offset + self.size
+    }
+}
+
+/// Pending writes to the ROM, kept as disjoint ranges.
+///
+/// A write replaces whatever was pending under it, so the most recent edit always
+/// wins, and is merged with the ranges it overlaps or touches. A write that covers
+/// only part of a run of pending data is a partial overlap: it usually means two edits
+/// disagree about what lives at those bytes, so it is reported to the caller.
+/// Only the merged runs are kept, not the boundaries of the edits that made them,
+/// so overlaps are checked against runs (see `partial_overlaps`).
+#[derive(Debug, Clone, Default)]
+pub struct ModificationStore {
+    ranges: BTreeMap<u32, Vec<u8>>,
+}
+
+impl ModificationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Offset and length of every range that intersects `offset..offset + len`,
+    /// counting touching ranges when `touching` is set.
+    fn intersecting(&self, offset: u32, len: u32, touching: bool) -> Vec<(u32, u32)> {
+        let end = offset + len;
+        let limit = if touching { end + 1 } else { end };
+        self.ranges
+            .range(..limit)
+            .map(|(&start, bytes)| (start, bytes.len() as u32))
+            .filter(|&(start, size)| {
+                let range_end = start + size;
+                if touching {
+                    range_end >= offset
+                } else {
+                    range_end > offset
+                }
+            })
+            .collect()
+    }
+
+    /// Pending ranges a write of `len` bytes at `offset` would only partly cover
+    /// or only partly fall within, as (offset, length) pairs.
+    ///
+    /// Ranges are merged as writes are made, so this compares against the merged
+    /// runs: rewriting part of an earlier edit is not a partial overlap, but a
+    /// write straddling the edge of pending data is.
+    pub fn partial_overlaps(&self, offset: u32, len: u32) -> Vec<(u32, u32)> {
+        let end = offset + len;
+        self.intersecting(offset, len, false)
+            .into_iter()
+            .filter(|&(start, size)| {
+                let range_end = start + size;
+                let covers_range = offset <= start && range_end <= end;
+                let within_range = start <= offset && end <= range_end;
+                !covers_range && !within_range
+            })
+            .collect()
+    }
+
+    /// Records a write, merging it over pending data.
+    /// Returns the partial overlaps the write made (see `partial_overlaps`).
+    pub fn write(&mut self, offset: u32, bytes: Vec<u8>) -> Vec<(u32, u32)> {
+        if bytes.is_empty() {
+            return Vec::new();
+        }
+        let len = bytes.len() as u32;
+        let overlaps = self.partial_overlaps(offset, len);
+
+        // Union of the write and every range it overlaps or touches
+        let neighbours = self.intersecting(offset, len, true);
+        let start = neighbours.first().map_or(offset, |&(s, _)| s.min(offset));
+        let end = neighbours
+            .iter()
+            .map(|&(s, size)| s + size)
+            .fold(offset + len, u32::max);
+
+        let mut merged = vec![0u8; (end - start) as usize];
+        for (range_start, _) in neighbours {
+            let old = self.ranges.remove(&range_start).unwrap_or_default();
+            let at = (range_start - start) as usize;
+            merged[at..at + old.len()].copy_from_slice(&old);
+        }
+        let at = (offset - start) as usize;
+        merged[at..at + bytes.len()].copy_from_slice(&bytes);
+        self.ranges.insert(start, merged);
+
+        overlaps
+    }
+
+    /// Drops pending bytes in `offset..offset + len`, splitting ranges that extend past it.
+    pub fn revert(&mut self, offset: u32, len: u32) {
+        let end = offset + len;
+        for (start, _) in self.intersecting(offset, len, false) {
+            let bytes = self.ranges.remove(&start).unwrap_or_default();
+            let range_end = start + bytes.len() as u32;
+            if start < offset {
+                self.ranges
+                    .insert(start, bytes[..(offset - start) as usize].to_vec());
+            }
+            if range_end > end {
+                self.ranges
+                    .insert(end, bytes[(end - start) as usize..].to_vec());
+            }
+        }
+    }
+
+    /// The pending bytes within `offset..offset + len`, clipped to the range.
+    pub fn pending(&self, offset: u32, len: u32) -> Vec<PendingWrite> {
+        let end = offset + len;
+        self.intersecting(offset, len, false)
+            .into_iter()
+            .map(|(start, size)| {
+                let bytes = &self.ranges[&start];
+                let from = start.max(offset);
+                let to = (start + size).min(end);
+                PendingWrite {
+                    offset: from,
+                    bytes: bytes[(from - start) as usize..(to - start) as usize].to_vec(),
+                }
+            })
+            .collect()
+    }
+
+    /// Every pending range, in offset order.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &Vec<u8>)> {
+        self.ranges.iter().map(|(&offset, bytes)| (offset, bytes))
+    }
+
+    /// Pending ranges starting before `end`, in offset order.
+    pub fn starting_before(&self, end: u32) -> impl Iterator<Item = (u32, &Vec<u8>)> {
+        self.ranges
+            .range(..end)
+            .map(|(&offset, bytes)| (offset, bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(store: &ModificationStore) -> Vec<(u32, Vec<u8>)> {
+        store.iter().map(|(o, b)| (o, b.clone())).collect()
+    }
+
+    #[test]
+    fn test_last_write_wins_and_coalesces() {
+        let mut store = ModificationStore::new();
+        assert!(store.write(4, vec![1, 1, 1, 1]).is_empty());
+        // Straddles the start of the pending range: flagged, newest bytes win
+        assert_eq!(store.write(2, vec![2, 2, 2]), vec![(4, 4)]);
+        // Touching write is merged without a flag
+        assert!(store.write(8, vec![3]).is_empty());
+        // Rewriting inside pending data is not a partial overlap
+        assert!(store.write(5, vec![4]).is_empty());
+        assert_eq!(ranges(&store), vec![(2, vec![2, 2, 2, 4, 1, 1, 3])]);
+
+        assert_eq!(
+            store.pending(0, 4),
+            vec![PendingWrite {
+                offset: 2,
+                bytes: vec![2, 2]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_revert_splits_ranges() {
+        let mut store = ModificationStore::new();
+        store.write(0, vec![1; 10]);
+        store.revert(3, 4);
+        assert_eq!(ranges(&store), vec![(0, vec![1; 3]), (7, vec![1; 3])]);
+        store.revert(0, 10);
+        assert!(store.is_empty());
+    }
+}
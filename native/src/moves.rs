@@ -0,0 +1,57 @@
+use crate::offsets::GameOffsets;
+use crate::structures::BattleMove;
+use crate::tables::{entry_offset, read_entry};
+use crate::text::{decode_text, read_text};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+pub const MOVE_SIZE: usize = 12;
+pub const MOVE_NAME_SIZE: usize = 13;
+
+/// A move's battle data with its name and description.
+/// Move 0 (none) has no description; it is always empty.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveInfo {
+    pub data: BattleMove,
+    pub name: String,
+    pub description: String,
+}
+
+/// Location of the description pointer of `move_id`, if the move has one.
+pub fn description_ptr_offset(offsets: &GameOffsets, move_id: u16) -> Option<u32> {
+    if move_id == 0 {
+        return None;
+    }
+    Some(offsets.move_descriptions + (move_id as u32 - 1) * 4)
+}
+
+pub fn read_move_name(data: &[u8], offsets: &GameOffsets, move_id: u16) -> Result<String> {
+    let offset = entry_offset(
+        offsets.move_names,
+        move_id as u32,
+        MOVE_NAME_SIZE,
+        offsets.move_count,
+    )?;
+    let start = offset as usize;
+    let end = (start + MOVE_NAME_SIZE).min(data.len());
+    Ok(decode_text(&data[start.min(end)..end]))
+}
+
+pub fn read_move(data: &[u8], offsets: &GameOffsets, move_id: u16) -> Result<MoveInfo> {
+    let offset = entry_offset(offsets.moves, move_id as u32, MOVE_SIZE, offsets.move_count)?;
+    let move_data = read_entry(data, offset, MOVE_SIZE)?;
+
+    let description = match description_ptr_offset(offsets, move_id) {
+        Some(ptr_offset) => {
+            let ptr: u32 = read_entry(data, ptr_offset, 4)?;
+            read_text(data, (ptr & 0x01FFFFFF) as usize)?
+        }
+        None => String::new(),
+    };
+
+    Ok(MoveInfo {
+        data: move_data,
+        name: read_move_name(data, offsets, move_id)?,
+        description,
+    })
+}
@@ -0,0 +1,317 @@
+use crate::pokedex::DexOrder;
+use anyhow::{bail, Context, Result};
+use serde::de::value::{Error as ValueError, MapDeserializer};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Offsets shipped with gbaforge, in the same format users can load.
+const BUILTIN_OFFSETS: &str = include_str!("offsets.ini");
+/// Longest chain of `base=` sections followed before assuming a cycle.
+const MAX_BASE_DEPTH: usize = 8;
+
+/// ROM offsets of the data tables the editors read, for one game.
+/// Offsets are file offsets (no 0x08 prefix).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameOffsets {
+    /// Wild encounter header table (gWildMonHeaders)
+    pub wild_pokemon: u32,
+    /// Base stats table (gSpeciesInfo), indexed by species id
+    pub base_stats: u32,
+    /// Entries in the species tables, including the empty species 0
+    pub species_count: u32,
+    /// Battle move table (gBattleMoves)
+    pub moves: u32,
+    /// Move names, 13 bytes each
+    pub move_names: u32,
+    /// Pointers to move descriptions, starting at move 1
+    pub move_descriptions: u32,
+    /// Entries in the move tables, including the empty move 0
+    pub move_count: u32,
+    /// Trainer table (gTrainers)
+    pub trainers: u32,
+    pub trainer_count: u32,
+    /// Item table (gItems)
+    pub items: u32,
+    pub item_count: u32,
+    /// Evolution table, 5 entries of 8 bytes per species
+    pub evolutions: u32,
+    /// Pointers to each species' level-up learnset
+    pub learnsets: u32,
+    /// TM/HM compatibility bitfields, 8 bytes per species
+    pub tm_compatibility: u32,
+    /// Move taught by each TM/HM
+    pub tm_moves: u32,
+    /// Move taught by each move tutor
+    pub tutor_moves: u32,
+    /// Tutor compatibility bitfields, one u16 (FireRed) or u32 (Emerald) per species
+    pub tutor_compatibility: u32,
+    pub tutor_count: u32,
+    /// Pokédex entries, indexed by national dex number
+    pub pokedex_entries: u32,
+    /// Entries in the Pokédex table, including the empty entry 0
+    pub pokedex_count: u32,
+    /// Species to regional dex number, starting at species 1
+    pub regional_dex: u32,
+    /// Species to national dex number, starting at species 1
+    pub national_dex: u32,
+    /// Regional dex number to national dex number, starting at 1
+    pub regional_to_national: u32,
+    /// Compressed front/back sprite tables, 8 bytes per species
+    pub front_sprites: u32,
+    pub back_sprites: u32,
+    /// Compressed normal/shiny palette tables, 8 bytes per species
+    pub normal_palettes: u32,
+    pub shiny_palettes: u32,
+    /// Pointers to each species' uncompressed menu icon
+    pub icons: u32,
+    /// Icon palette used by each species, one byte per species
+    pub icon_palette_indices: u32,
+    /// Icon palette table, 8 bytes per palette
+    pub icon_palettes: u32,
+    /// Pointers to each species' footprint
+    pub footprints: u32,
+    /// Pointers to each overworld sprite template, indexed by graphics id
+    pub overworld_sprites: u32,
+    pub overworld_sprite_count: u32,
+    /// Overworld sprite palettes, 8 bytes each, ending with tag 0x11FF
+    pub overworld_palettes: u32,
+    /// Species names, 11 bytes each. 0 if unknown, which prevents expanding species.
+    #[serde(default)]
+    pub species_names: u32,
+    /// Cry and reversed cry tables, 12 bytes per cry. They are indexed by cry id,
+    /// which the game maps from the species id.
+    #[serde(default)]
+    pub cries: u32,
+    #[serde(default)]
+    pub reverse_cries: u32,
+    #[serde(default)]
+    pub cry_count: u32,
+    /// Where free space begins; allocations never go below it.
+    /// 0 if unknown, in which case nothing can be allocated.
+    #[serde(default)]
+    pub free_space_start: u32,
+    /// Pointers to each map bank's list of map header pointers.
+    /// 0 if unknown, in which case maps are left out of ROM diffs.
+    #[serde(default)]
+    pub map_banks: u32,
+    #[serde(default)]
+    pub map_bank_count: u32,
+}
+
+impl GameOffsets {
+    /// Bytes of tutor compatibility per species: enough bits for every tutor.
+    pub fn tutor_compat_size(&self) -> usize {
+        if self.tutor_count > 16 {
+            4
+        } else {
+            2
+        }
+    }
+
+    /// The offsets as a complete INI section, e.g. to save moved tables next to a ROM.
+    /// Counts are written in decimal, offsets in hex.
+    pub fn to_ini(&self, section: &str, name: &str) -> Result<String> {
+        let value = serde_json::to_value(self)?;
+        let Some(fields) = value.as_object() else {
+            bail!("Offsets did not serialise to fields");
+        };
+        let mut text = format!("[{}]\nname={}\n", section, name);
+        for (key, value) in fields {
+            let number = value.as_u64().unwrap_or_default();
+            if key.ends_with("_count") {
+                text.push_str(&format!("{}={}\n", key, number));
+            } else {
+                text.push_str(&format!("{}=0x{:X}\n", key, number));
+            }
+        }
+        Ok(text)
+    }
+
+    /// Sets the fields that differ between `from` and `to` to their value in `to`,
+    /// keeping the others, e.g. to undo one table move without undoing later ones.
+    pub fn apply_changes(&mut self, from: &GameOffsets, to: &GameOffsets) -> Result<()> {
+        let from = serde_json::to_value(from)?;
+        let to = serde_json::to_value(to)?;
+        let mut fields = serde_json::to_value(&*self)?;
+        if let (Some(from), Some(to), Some(fields)) =
+            (from.as_object(), to.as_object(), fields.as_object_mut())
+        {
+            for (key, value) in to {
+                if from.get(key) != Some(value) {
+                    fields.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        *self = serde_json::from_value(fields)?;
+        Ok(())
+    }
+
+    /// Start of a dex number table. The tables have no entry for species 0.
+    pub fn dex_order(&self, order: DexOrder) -> u32 {
+        match order {
+            DexOrder::Regional => self.regional_dex,
+            DexOrder::National => self.national_dex,
+            DexOrder::RegionalToNational => self.regional_to_national,
+        }
+    }
+}
+
+/// Table offsets per game and revision, read from INI files.
+///
+/// Each section is named after a game code, with the revision appended for
+/// revisions after 1.0 (`[BPRE1]` is FireRed 1.1). Keys are `GameOffsets` field
+/// names, plus `name` for a description and `base` to inherit another section's keys.
+/// Values are hex with a `0x` prefix, or decimal.
+#[derive(Debug, Clone, Default)]
+pub struct OffsetDatabase {
+    sections: HashMap<String, BTreeMap<String, String>>,
+}
+
+fn parse_number(value: &str) -> Result<u32> {
+    let value = value.trim();
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .with_context(|| format!("Invalid number \"{}\"", value))
+}
+
+impl OffsetDatabase {
+    /// The offsets for the vanilla games gbaforge ships with.
+    pub fn builtin() -> Self {
+        let mut database = Self::default();
+        database
+            .load_ini(BUILTIN_OFFSETS)
+            .expect("Built-in offsets.ini is invalid");
+        database
+    }
+
+    /// Name of the section holding the offsets of a game revision.
+    pub fn section_name(game_code: &str, revision: u8) -> String {
+        if revision == 0 {
+            game_code.to_string()
+        } else {
+            format!("{}{}", game_code, revision)
+        }
+    }
+
+    /// Adds the sections of an INI file, replacing sections with the same name.
+    /// Every section is checked to resolve to a complete set of offsets.
+    /// Returns the names of the sections loaded.
+    pub fn load_ini(&mut self, text: &str) -> Result<Vec<String>> {
+        let mut parsed: Vec<(String, BTreeMap<String, String>)> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                parsed.push((name.trim().to_string(), BTreeMap::new()));
+            } else if let Some((key, value)) = line.split_once('=') {
+                let Some((_, section)) = parsed.last_mut() else {
+                    bail!("Line {}: key outside of a section", number + 1);
+                };
+                section.insert(key.trim().to_lowercase(), value.trim().to_string());
+            } else {
+                bail!("Line {}: expected [section] or key=value", number + 1);
+            }
+        }
+
+        let mut candidate = self.clone();
+        let names: Vec<String> = parsed.iter().map(|(name, _)| name.clone()).collect();
+        candidate.sections.extend(parsed);
+        for name in &names {
+            candidate
+                .resolve(name)
+                .with_context(|| format!("Invalid offsets in section [{}]", name))?;
+        }
+        *self = candidate;
+        Ok(names)
+    }
+
+    /// Keys of a section, with those of its `base` sections filled in.
+    fn merged_keys(&self, name: &str) -> Result<BTreeMap<String, String>> {
+        let mut keys = BTreeMap::new();
+        let mut current = name.to_string();
+        for _ in 0..MAX_BASE_DEPTH {
+            let Some(section) = self.sections.get(&current) else {
+                bail!("No offsets section [{}]", current);
+            };
+            for (key, value) in section {
+                keys.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+            match section.get("base") {
+                Some(base) => current = base.clone(),
+                None => return Ok(keys),
+            }
+        }
+        bail!("Section [{}] has too many base sections", name)
+    }
+
+    /// Builds the offsets of a section.
+    pub fn resolve(&self, name: &str) -> Result<GameOffsets> {
+        let values = self
+            .merged_keys(name)?
+            .into_iter()
+            .filter(|(key, _)| key != "name" && key != "base")
+            .map(|(key, value)| Ok((key, parse_number(&value)?)))
+            .collect::<Result<Vec<(String, u32)>>>()?;
+        GameOffsets::deserialize(MapDeserializer::<_, ValueError>::new(values.into_iter()))
+            .map_err(|e| anyhow::anyhow!("{}", e))
+    }
+
+    /// Offsets of a game revision, if the database has a section for it.
+    pub fn lookup(&self, game_code: &str, revision: u8) -> Option<GameOffsets> {
+        self.resolve(&Self::section_name(game_code, revision)).ok()
+    }
+
+    pub fn section_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.sections.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_and_inherited_sections() {
+        let mut database = OffsetDatabase::builtin();
+        let fire_red = database.lookup("BPRE", 0).unwrap();
+        assert_eq!(fire_red.base_stats, 0x254784);
+        let fire_red_1_1 = database.lookup("BPRE", 1).unwrap();
+        assert_eq!(fire_red_1_1.base_stats, 0x2547F4);
+        assert_eq!(fire_red_1_1.species_count, fire_red.species_count);
+        assert!(database.lookup("BPGE", 1).is_some());
+        assert!(database.lookup("BPRE", 2).is_none());
+
+        let loaded = database
+            .load_ini(
+                "; expanded hack\n[MYHACK]\nbase=BPRE\nbase_stats=0x800000\nspecies_count=1000\n",
+            )
+            .unwrap();
+        assert_eq!(loaded, vec!["MYHACK"]);
+        let hack = database.resolve("MYHACK").unwrap();
+        assert_eq!(hack.base_stats, 0x800000);
+        assert_eq!(hack.species_count, 1000);
+        assert_eq!(hack.moves, fire_red.moves);
+
+        // Incomplete sections are rejected and leave the database unchanged
+        assert!(database.load_ini("[AXVE]\nbase_stats=0x1FEC18\n").is_err());
+        assert!(!database.section_names().contains(&"AXVE".to_string()));
+
+        // Offsets written with to_ini read back unchanged, without a base section
+        let mut moved = fire_red.clone();
+        moved.wild_pokemon = 0x720000;
+        let mut saved = OffsetDatabase::default();
+        saved
+            .load_ini(&moved.to_ini("BPRE", "Saved hack").unwrap())
+            .unwrap();
+        assert_eq!(saved.lookup("BPRE", 0), Some(moved));
+    }
+}
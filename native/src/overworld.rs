@@ -0,0 +1,145 @@
+use crate::structures::{MapEvents, ObjectEventTemplate, OverworldGraphicsInfo};
+use crate::tables::read_entry;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+pub const OVERWORLD_INFO_SIZE: usize = 36;
+pub const OBJECT_EVENT_SIZE: usize = 24;
+const MAP_EVENTS_SIZE: usize = 20;
+/// Each frame image is a pointer to uncompressed 4bpp tiles followed by its size.
+const FRAME_IMAGE_SIZE: u32 = 8;
+const MAX_FRAMES: usize = 32;
+/// Sprite palette table entries: pointer, tag, padding.
+const SPRITE_PALETTE_SIZE: u32 = 8;
+/// Tag ending the overworld sprite palette table.
+pub const PALETTE_TAG_NONE: u16 = 0x11FF;
+const MAX_PALETTES: u32 = 64;
+
+/// A decoded overworld sprite template.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OverworldSprite {
+    pub tile_tag: u16,
+    pub palette_tag: u16,
+    pub reflection_palette_tag: u16,
+    pub width: u16,
+    pub height: u16,
+    pub palette_slot: u8,
+    pub shadow_size: u8,
+    pub inanimate: bool,
+    pub tracks: u8,
+    pub anims_ptr: u32,
+    /// ROM pointers to the graphics of each frame.
+    pub frames: Vec<u32>,
+}
+
+fn read_u32(data: &[u8], offset: u32) -> Option<u32> {
+    read_entry(data, offset, 4).ok()
+}
+
+/// Reads the frame image list. Its length is not stored, so frames are read while
+/// they have the template's frame size.
+pub fn read_frame_images(data: &[u8], info: &OverworldGraphicsInfo) -> Vec<u32> {
+    let start = info.images_ptr & 0x01FFFFFF;
+    (0..MAX_FRAMES as u32)
+        .map_while(|i| {
+            let entry = start + i * FRAME_IMAGE_SIZE;
+            let ptr = read_u32(data, entry)?;
+            let size: u16 = read_entry(data, entry + 4, 2).ok()?;
+            let valid = (0x08000000..0x0A000000).contains(&ptr)
+                && ((ptr & 0x01FFFFFF) as usize) < data.len();
+            (valid && size == info.size).then_some(ptr)
+        })
+        .collect()
+}
+
+pub fn decode_overworld_sprite(data: &[u8], info: &OverworldGraphicsInfo) -> OverworldSprite {
+    OverworldSprite {
+        tile_tag: info.tile_tag,
+        palette_tag: info.palette_tag,
+        reflection_palette_tag: info.reflection_palette_tag,
+        width: info.width as u16,
+        height: info.height as u16,
+        palette_slot: info.flags & 0xF,
+        shadow_size: (info.flags >> 4) & 0x3,
+        inanimate: info.flags & 0x40 != 0,
+        tracks: info.tracks,
+        anims_ptr: info.anims_ptr,
+        frames: read_frame_images(data, info),
+    }
+}
+
+/// Finds the palette with `tag` in the overworld sprite palette table.
+pub fn find_sprite_palette(data: &[u8], table: u32, tag: u16) -> Result<u32> {
+    for i in 0..MAX_PALETTES {
+        let entry = table + i * SPRITE_PALETTE_SIZE;
+        let entry_tag: u16 = read_entry(data, entry + 4, 2)?;
+        if entry_tag == PALETTE_TAG_NONE {
+            break;
+        }
+        if entry_tag == tag {
+            return read_entry(data, entry, 4);
+        }
+    }
+    bail!("No overworld palette with tag {:04x}", tag)
+}
+
+/// Reads the person events of a map.
+pub fn read_object_events(data: &[u8], event_data_ptr: u32) -> Result<Vec<ObjectEventTemplate>> {
+    let events: MapEvents = read_entry(data, event_data_ptr & 0x01FFFFFF, MAP_EVENTS_SIZE)?;
+    let start = events.object_events_ptr & 0x01FFFFFF;
+    (0..events.object_count as u32)
+        .map(|i| {
+            read_entry(
+                data,
+                start + i * OBJECT_EVENT_SIZE as u32,
+                OBJECT_EVENT_SIZE,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_and_palette() {
+        let mut rom = vec![0u8; 0x200];
+        // Frame images at 0x40: two 16x32 frames (256 bytes), then an unrelated entry
+        for (i, ptr) in [0x08000100u32, 0x08000180, 0x08000100].iter().enumerate() {
+            let entry = 0x40 + i * 8;
+            rom[entry..entry + 4].copy_from_slice(&ptr.to_le_bytes());
+            let size: u16 = if i < 2 { 256 } else { 128 };
+            rom[entry + 4..entry + 6].copy_from_slice(&size.to_le_bytes());
+        }
+        // Palette table at 0x80: tag 0x1103, tag 0x1104, terminator
+        for (i, tag) in [0x1103u16, 0x1104, PALETTE_TAG_NONE].iter().enumerate() {
+            let entry = 0x80 + i * 8;
+            rom[entry..entry + 4].copy_from_slice(&(0x08000C00 + i as u32 * 32).to_le_bytes());
+            rom[entry + 4..entry + 6].copy_from_slice(&tag.to_le_bytes());
+        }
+
+        let info = OverworldGraphicsInfo {
+            tile_tag: 0xFFFF,
+            palette_tag: 0x1104,
+            reflection_palette_tag: 0x1104,
+            size: 256,
+            width: 16,
+            height: 32,
+            flags: 0x12,
+            tracks: 1,
+            padding: 0,
+            oam_ptr: 0,
+            subsprite_tables_ptr: 0,
+            anims_ptr: 0,
+            images_ptr: 0x08000040,
+            affine_anims_ptr: 0,
+        };
+        let sprite = decode_overworld_sprite(&rom, &info);
+        assert_eq!(sprite.frames, vec![0x08000100, 0x08000180]);
+        assert_eq!(sprite.palette_slot, 2);
+        assert_eq!(sprite.shadow_size, 1);
+        assert_eq!(find_sprite_palette(&rom, 0x80, 0x1104).unwrap(), 0x08000C20);
+        assert!(find_sprite_palette(&rom, 0x80, 0x1105).is_err());
+    }
+}
@@ -0,0 +1,575 @@
+use crate::checksum::crc32;
+use crate::modifications::MAX_ROM_SIZE;
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PatchFormat {
+    Ips,
+    Ups,
+    Bps,
+}
+
+/// IPS offsets are 24-bit, so nothing at or past 16 MB can be patched.
+const IPS_MAX_OFFSET: usize = 0x1000000;
+const IPS_MAX_RECORD: usize = 0xFFFF;
+/// A record at this offset would read as the "EOF" footer.
+const IPS_EOF_OFFSET: usize = 0x454F46;
+/// Shortest run of one byte worth an RLE record instead of literal data.
+const IPS_MIN_RLE: usize = 8;
+
+/// Byte of `data` at `i`; bytes past the end read as 0, as UPS and IPS expansion assume.
+fn byte_at(data: &[u8], i: usize) -> u8 {
+    data.get(i).copied().unwrap_or(0)
+}
+
+/// Ranges where `target` differs from `source`, as (start, end).
+/// Bytes past the end of `source` always count as changed.
+pub fn differences(source: &[u8], target: &[u8]) -> Vec<(usize, usize)> {
+    let mut runs = Vec::new();
+    let mut i = 0;
+    while i < target.len() {
+        if i < source.len() && source[i] == target[i] {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < target.len() && (i >= source.len() || source[i] != target[i]) {
+            i += 1;
+        }
+        runs.push((start, i));
+    }
+    runs
+}
+
+/// UPS/BPS variable-length integer.
+pub fn encode_varint(mut value: u64, out: &mut Vec<u8>) {
+    loop {
+        let low = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(0x80 | low);
+            return;
+        }
+        out.push(low);
+        value -= 1;
+    }
+}
+
+/// Reads a UPS/BPS variable-length integer at `pos`, advancing it.
+pub fn decode_varint(data: &[u8], pos: &mut usize) -> Result<u64> {
+    let mut value = 0u64;
+    let mut shift = 1u64;
+    loop {
+        let byte = *data
+            .get(*pos)
+            .ok_or_else(|| anyhow!("Patch is truncated"))?;
+        *pos += 1;
+        value = value
+            .checked_add((byte & 0x7F) as u64 * shift)
+            .ok_or_else(|| anyhow!("Patch has an invalid number"))?;
+        if byte & 0x80 != 0 {
+            return Ok(value);
+        }
+        shift = shift
+            .checked_mul(128)
+            .ok_or_else(|| anyhow!("Patch has an invalid number"))?;
+        value = value
+            .checked_add(shift)
+            .ok_or_else(|| anyhow!("Patch has an invalid number"))?;
+    }
+}
+
+/// Takes `len` bytes of the patch at `pos`, advancing it.
+fn take<'a>(patch: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8]> {
+    let bytes = patch
+        .get(*pos..*pos + len)
+        .ok_or_else(|| anyhow!("Patch is truncated"))?;
+    *pos += len;
+    Ok(bytes)
+}
+
+fn read_u32_le(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Splits off the trailing source, target and patch CRC32s of a UPS or BPS
+/// patch, failing if the patch itself is damaged or made for another source.
+fn verify_footer<'a>(patch: &'a [u8], source: &[u8]) -> Result<(&'a [u8], u32)> {
+    if patch.len() < 12 {
+        bail!("Patch is truncated");
+    }
+    let (body, footer) = patch.split_at(patch.len() - 12);
+    if crc32(&patch[..patch.len() - 4]) != read_u32_le(&footer[8..]) {
+        bail!("Patch checksum does not match; the file is damaged");
+    }
+    let source_crc = read_u32_le(&footer[..4]);
+    if crc32(source) != source_crc {
+        bail!(
+            "Patch was made for a ROM with CRC32 {:08X}, but this one is {:08X}",
+            source_crc,
+            crc32(source)
+        );
+    }
+    Ok((body, read_u32_le(&footer[4..8])))
+}
+
+fn verify_target(target: &[u8], expected: u32) -> Result<()> {
+    if crc32(target) != expected {
+        bail!(
+            "Patched ROM has CRC32 {:08X}, expected {:08X}",
+            crc32(target),
+            expected
+        );
+    }
+    Ok(())
+}
+
+fn push_ips_record(out: &mut Vec<u8>, offset: usize, bytes: &[u8]) {
+    out.extend(&(offset as u32).to_be_bytes()[1..]);
+    let rle = bytes.len() >= IPS_MIN_RLE && bytes.iter().all(|&b| b == bytes[0]);
+    if rle {
+        out.extend([0, 0]);
+        out.extend((bytes.len() as u16).to_be_bytes());
+        out.push(bytes[0]);
+    } else {
+        out.extend((bytes.len() as u16).to_be_bytes());
+        out.extend(bytes);
+    }
+}
+
+/// Builds an IPS patch turning `source` into `target`.
+/// Fails when a change lies beyond the 16 MB IPS can address. A shorter target is
+/// recorded with the truncation extension after the footer.
+pub fn create_ips(source: &[u8], target: &[u8]) -> Result<Vec<u8>> {
+    let mut out = b"PATCH".to_vec();
+    for (mut start, end) in differences(source, target) {
+        if start == IPS_EOF_OFFSET {
+            // Include the unchanged byte before so the record does not read as "EOF"
+            start -= 1;
+        }
+        if end > IPS_MAX_OFFSET {
+            bail!("ROM has changes past the 16 MB IPS can address; export as UPS or BPS");
+        }
+        let mut pos = start;
+        while pos < end {
+            let mut len = (end - pos).min(IPS_MAX_RECORD);
+            if pos + len == IPS_EOF_OFFSET && pos + len < end {
+                len -= 1;
+            }
+            push_ips_record(&mut out, pos, &target[pos..pos + len]);
+            pos += len;
+        }
+    }
+    out.extend(b"EOF");
+    if target.len() < source.len() {
+        out.extend(&(target.len() as u32).to_be_bytes()[1..]);
+    }
+    Ok(out)
+}
+
+/// Builds a UPS patch: XOR runs between the two files plus source, target and patch CRC32s.
+pub fn create_ups(source: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut out = b"UPS1".to_vec();
+    encode_varint(source.len() as u64, &mut out);
+    encode_varint(target.len() as u64, &mut out);
+
+    let size = source.len().max(target.len());
+    let mut pos = 0;
+    let mut i = 0;
+    while i < size {
+        if byte_at(source, i) == byte_at(target, i) {
+            i += 1;
+            continue;
+        }
+        encode_varint((i - pos) as u64, &mut out);
+        while i < size && byte_at(source, i) != byte_at(target, i) {
+            out.push(byte_at(source, i) ^ byte_at(target, i));
+            i += 1;
+        }
+        out.push(0);
+        i += 1;
+        pos = i;
+    }
+
+    out.extend(crc32(source).to_le_bytes());
+    out.extend(crc32(target).to_le_bytes());
+    let patch_crc = crc32(&out);
+    out.extend(patch_crc.to_le_bytes());
+    out
+}
+
+const BPS_SOURCE_READ: u64 = 0;
+const BPS_TARGET_READ: u64 = 1;
+
+fn push_bps_action(out: &mut Vec<u8>, action: u64, len: usize) {
+    encode_varint(((len as u64 - 1) << 2) | action, out);
+}
+
+/// Builds a BPS patch. Unchanged bytes are copied from the source at the same
+/// offset and changed bytes are stored literally, with source, target and patch CRC32s.
+pub fn create_bps(source: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut out = b"BPS1".to_vec();
+    encode_varint(source.len() as u64, &mut out);
+    encode_varint(target.len() as u64, &mut out);
+    encode_varint(0, &mut out);
+
+    let mut pos = 0;
+    for (start, end) in differences(source, target) {
+        if start > pos {
+            push_bps_action(&mut out, BPS_SOURCE_READ, start - pos);
+        }
+        push_bps_action(&mut out, BPS_TARGET_READ, end - start);
+        out.extend(&target[start..end]);
+        pos = end;
+    }
+    if target.len() > pos {
+        push_bps_action(&mut out, BPS_SOURCE_READ, target.len() - pos);
+    }
+
+    out.extend(crc32(source).to_le_bytes());
+    out.extend(crc32(target).to_le_bytes());
+    let patch_crc = crc32(&out);
+    out.extend(patch_crc.to_le_bytes());
+    out
+}
+
+/// Identifies a patch file by its magic bytes.
+pub fn detect_format(patch: &[u8]) -> Result<PatchFormat> {
+    if patch.starts_with(b"PATCH") {
+        Ok(PatchFormat::Ips)
+    } else if patch.starts_with(b"UPS1") {
+        Ok(PatchFormat::Ups)
+    } else if patch.starts_with(b"BPS1") {
+        Ok(PatchFormat::Bps)
+    } else {
+        bail!("Unrecognised patch format")
+    }
+}
+
+/// Applies an IPS patch, including RLE records and the truncation extension.
+/// IPS has no checksums, so a patch for the wrong ROM is not detected.
+pub fn apply_ips(source: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    let mut out = source.to_vec();
+    let mut pos = 5;
+    loop {
+        let offset = take(patch, &mut pos, 3)?;
+        if offset == b"EOF" {
+            break;
+        }
+        let offset = u32::from_be_bytes([0, offset[0], offset[1], offset[2]]) as usize;
+        let size = take(patch, &mut pos, 2)?;
+        let size = u16::from_be_bytes([size[0], size[1]]) as usize;
+        let bytes = if size == 0 {
+            let run = take(patch, &mut pos, 3)?;
+            vec![run[2]; u16::from_be_bytes([run[0], run[1]]) as usize]
+        } else {
+            take(patch, &mut pos, size)?.to_vec()
+        };
+        let end = offset + bytes.len();
+        if end > out.len() {
+            out.resize(end, 0);
+        }
+        out[offset..end].copy_from_slice(&bytes);
+    }
+    if let Ok(size) = take(patch, &mut pos, 3) {
+        out.truncate(u32::from_be_bytes([0, size[0], size[1], size[2]]) as usize);
+    }
+    Ok(out)
+}
+
+/// Rejects a patch-declared ROM size larger than the cartridge address space,
+/// before anything is allocated for it.
+fn check_target_size(target_size: usize) -> Result<()> {
+    if target_size > MAX_ROM_SIZE as usize {
+        bail!(
+            "Patch makes a {} byte ROM, larger than the {} MB maximum",
+            target_size,
+            MAX_ROM_SIZE >> 20
+        );
+    }
+    Ok(())
+}
+
+/// Applies a UPS patch after checking the patch and source CRC32s, then checks the result.
+pub fn apply_ups(source: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    let (body, target_crc) = verify_footer(patch, source)?;
+    let mut pos = 4;
+    let source_size = decode_varint(body, &mut pos)? as usize;
+    let target_size = decode_varint(body, &mut pos)? as usize;
+    if source_size != source.len() {
+        bail!(
+            "Patch expects a {} byte ROM, but this one is {} bytes",
+            source_size,
+            source.len()
+        );
+    }
+    check_target_size(target_size)?;
+
+    let mut out = source.to_vec();
+    out.resize(source_size.max(target_size), 0);
+    let mut offset: usize = 0;
+    while pos < body.len() {
+        let skip = decode_varint(body, &mut pos)? as usize;
+        offset = offset
+            .checked_add(skip)
+            .ok_or_else(|| anyhow!("Patch is corrupt"))?;
+        loop {
+            let xor = *body.get(pos).ok_or_else(|| anyhow!("Patch is truncated"))?;
+            pos += 1;
+            if xor == 0 {
+                break;
+            }
+            *out.get_mut(offset)
+                .ok_or_else(|| anyhow!("Patch writes past the end of the ROM"))? ^= xor;
+            offset += 1;
+        }
+        offset = offset
+            .checked_add(1)
+            .ok_or_else(|| anyhow!("Patch is corrupt"))?;
+    }
+    out.truncate(target_size);
+
+    verify_target(&out, target_crc)?;
+    Ok(out)
+}
+
+const BPS_SOURCE_COPY: u64 = 2;
+
+/// Decodes a BPS relative offset: the low bit is the sign.
+fn bps_relative(body: &[u8], pos: &mut usize, base: usize) -> Result<usize> {
+    let value = decode_varint(body, pos)?;
+    let delta = (value >> 1) as usize;
+    let moved = if value & 1 != 0 {
+        base.checked_sub(delta)
+    } else {
+        base.checked_add(delta)
+    };
+    moved.ok_or_else(|| anyhow!("Patch has an invalid copy offset"))
+}
+
+/// Applies a BPS patch after checking the patch and source CRC32s, then checks the result.
+pub fn apply_bps(source: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    let (body, target_crc) = verify_footer(patch, source)?;
+    let mut pos = 4;
+    let source_size = decode_varint(body, &mut pos)? as usize;
+    let target_size = decode_varint(body, &mut pos)? as usize;
+    let metadata_size = decode_varint(body, &mut pos)? as usize;
+    take(body, &mut pos, metadata_size)?;
+    if source_size != source.len() {
+        bail!(
+            "Patch expects a {} byte ROM, but this one is {} bytes",
+            source_size,
+            source.len()
+        );
+    }
+    check_target_size(target_size)?;
+
+    let mut out = Vec::with_capacity(target_size);
+    let mut source_relative = 0;
+    let mut target_relative = 0;
+    while pos < body.len() {
+        let data = decode_varint(body, &mut pos)?;
+        let len = (data >> 2) as usize + 1;
+        if len > target_size - out.len() {
+            bail!("Patch writes past the end of the ROM");
+        }
+        match data & 3 {
+            BPS_SOURCE_READ => {
+                let start = out.len();
+                let bytes = source
+                    .get(start..start + len)
+                    .ok_or_else(|| anyhow!("Patch reads past the end of the ROM"))?;
+                out.extend_from_slice(bytes);
+            }
+            BPS_TARGET_READ => out.extend_from_slice(take(body, &mut pos, len)?),
+            BPS_SOURCE_COPY => {
+                source_relative = bps_relative(body, &mut pos, source_relative)?;
+                let end = source_relative
+                    .checked_add(len)
+                    .ok_or_else(|| anyhow!("Patch is corrupt"))?;
+                let bytes = source
+                    .get(source_relative..end)
+                    .ok_or_else(|| anyhow!("Patch reads past the end of the ROM"))?;
+                out.extend_from_slice(bytes);
+                source_relative = end;
+            }
+            _ => {
+                target_relative = bps_relative(body, &mut pos, target_relative)?;
+                for _ in 0..len {
+                    // Byte by byte: the copy may overlap the data it produces
+                    let byte = *out
+                        .get(target_relative)
+                        .ok_or_else(|| anyhow!("Patch has an invalid copy offset"))?;
+                    out.push(byte);
+                    target_relative += 1;
+                }
+            }
+        }
+    }
+    if out.len() != target_size {
+        bail!("Patch is truncated");
+    }
+
+    verify_target(&out, target_crc)?;
+    Ok(out)
+}
+
+/// Applies a patch in any supported format to `source`.
+pub fn patch_rom(source: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    match detect_format(patch)? {
+        PatchFormat::Ips => apply_ips(source, patch),
+        PatchFormat::Ups => apply_ups(source, patch),
+        PatchFormat::Bps => apply_bps(source, patch),
+    }
+}
+
+pub fn create_patch(format: PatchFormat, source: &[u8], target: &[u8]) -> Result<Vec<u8>> {
+    match format {
+        PatchFormat::Ips => create_ips(source, target),
+        PatchFormat::Ups => Ok(create_ups(source, target)),
+        PatchFormat::Bps => Ok(create_bps(source, target)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ips_records() {
+        let source = vec![0u8; 0x460000];
+        let mut target = source.clone();
+        target[0x10] = 1;
+        target[IPS_EOF_OFFSET] = 2;
+        target[0x200..0x220].fill(7);
+
+        let patch = create_ips(&source, &target).unwrap();
+        assert_eq!(&patch[..5], b"PATCH");
+        // Plain record at 0x10
+        assert_eq!(&patch[5..11], &[0x00, 0x00, 0x10, 0x00, 0x01, 0x01]);
+        // RLE record at 0x200
+        assert_eq!(
+            &patch[11..19],
+            &[0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x20, 0x07]
+        );
+        // The change at "EOF" starts a byte early
+        assert_eq!(&patch[19..22], &[0x45, 0x4F, 0x45]);
+        assert!(patch.ends_with(b"EOF"));
+
+        let mut large = vec![0u8; 0x1000010];
+        large[0x1000004] = 1;
+        assert!(create_ips(&vec![0u8; 0x1000010], &large).is_err());
+    }
+
+    #[test]
+    fn test_ups_and_bps_checksums() {
+        let source = b"Hello, Kanto!".to_vec();
+        let target = b"Hello, Hoenn!!".to_vec();
+        for patch in [create_ups(&source, &target), create_bps(&source, &target)] {
+            let (body, footer) = patch.split_at(patch.len() - 4);
+            assert_eq!(footer, crc32(body).to_le_bytes());
+            let target_crc = &patch[patch.len() - 8..patch.len() - 4];
+            assert_eq!(target_crc, crc32(&target).to_le_bytes());
+        }
+    }
+
+    #[test]
+    fn test_patch_round_trip() {
+        let source: Vec<u8> = (0..0x2000u32).map(|i| (i * 7) as u8).collect();
+        let mut target = source.clone();
+        target[0x100..0x140].fill(0xAA);
+        target[0x1FFF] = 0;
+        target.extend([1, 2, 3]);
+        let shorter = source[..0x1800].to_vec();
+
+        for format in [PatchFormat::Ips, PatchFormat::Ups, PatchFormat::Bps] {
+            for expected in [&target, &shorter] {
+                let patch = create_patch(format, &source, expected).unwrap();
+                assert_eq!(detect_format(&patch).unwrap(), format);
+                assert_eq!(&patch_rom(&source, &patch).unwrap(), expected);
+            }
+        }
+
+        // Checksummed formats reject the wrong source and damaged patches
+        let mut other = source.clone();
+        other[0] ^= 1;
+        let mut patch = create_bps(&source, &target);
+        assert!(patch_rom(&other, &patch).is_err());
+        patch[6] ^= 1;
+        assert!(patch_rom(&source, &patch).is_err());
+        assert!(patch_rom(&other, &create_ups(&source, &target)).is_err());
+    }
+
+    #[test]
+    fn test_bps_copy_actions() {
+        let source = b"ABCDEF".to_vec();
+        let target = b"DEFxyxyxy".to_vec();
+        let mut patch = b"BPS1".to_vec();
+        encode_varint(6, &mut patch);
+        encode_varint(9, &mut patch);
+        encode_varint(0, &mut patch);
+        // SourceCopy 3 bytes from +3
+        encode_varint((2 << 2) | BPS_SOURCE_COPY, &mut patch);
+        encode_varint(3 << 1, &mut patch);
+        // TargetRead "xy"
+        encode_varint((1 << 2) | BPS_TARGET_READ, &mut patch);
+        patch.extend(b"xy");
+        // TargetCopy 4 bytes from +3, overlapping its own output
+        encode_varint((3 << 2) | 3, &mut patch);
+        encode_varint(3 << 1, &mut patch);
+        patch.extend(crc32(&source).to_le_bytes());
+        patch.extend(crc32(&target).to_le_bytes());
+        let patch_crc = crc32(&patch);
+        patch.extend(patch_crc.to_le_bytes());
+
+        assert_eq!(apply_bps(&source, &patch).unwrap(), target);
+    }
+
+    #[test]
+    fn test_bps_sizes_are_bounded() {
+        let source = b"ABCDEF".to_vec();
+        let bps = |target_size: u64, actions: &[u8]| {
+            let mut patch = b"BPS1".to_vec();
+            encode_varint(6, &mut patch);
+            encode_varint(target_size, &mut patch);
+            encode_varint(0, &mut patch);
+            patch.extend(actions);
+            patch.extend(crc32(&source).to_le_bytes());
+            patch.extend(0u32.to_le_bytes());
+            let patch_crc = crc32(&patch);
+            patch.extend(patch_crc.to_le_bytes());
+            patch
+        };
+
+        // A declared size past 32 MB is rejected before anything is allocated
+        assert!(apply_bps(&source, &bps(1 << 40, &[])).is_err());
+        // A TargetCopy longer than the declared size is rejected before it runs
+        let mut actions = Vec::new();
+        encode_varint(BPS_TARGET_READ, &mut actions);
+        actions.push(b'x');
+        encode_varint(((1 << 40) << 2) | 3, &mut actions);
+        encode_varint(1, &mut actions);
+        assert!(apply_bps(&source, &bps(4, &actions)).is_err());
+    }
+
+    #[test]
+    fn test_ups_offsets_cannot_overflow() {
+        let source = b"ABCDEF".to_vec();
+        let mut patch = b"UPS1".to_vec();
+        encode_varint(6, &mut patch);
+        encode_varint(6, &mut patch);
+        // Two empty hunks whose skips add up past usize::MAX
+        for _ in 0..2 {
+            encode_varint(1 << 63, &mut patch);
+            patch.push(0);
+        }
+        patch.extend(crc32(&source).to_le_bytes());
+        patch.extend(crc32(&source).to_le_bytes());
+        let patch_crc = crc32(&patch);
+        patch.extend(patch_crc.to_le_bytes());
+
+        let err = apply_ups(&source, &patch).unwrap_err();
+        assert_eq!(err.to_string(), "Patch is corrupt");
+    }
+}
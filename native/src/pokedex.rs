@@ -0,0 +1,174 @@
+use crate::text::{decode_text, encode_fixed_text, read_text};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+pub const CATEGORY_NAME_SIZE: usize = 12;
+
+/// Layout of a Pokédex entry.
+/// FireRed keeps Ruby/Sapphire's two description pointers (the second page is unused
+/// in game); Emerald has a single description.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PokedexFormat {
+    TwoPages,
+    OnePage,
+}
+
+impl PokedexFormat {
+    pub fn from_game_code(game_code: &str) -> Self {
+        match game_code {
+            "BPEE" => PokedexFormat::OnePage,
+            _ => PokedexFormat::TwoPages,
+        }
+    }
+
+    pub fn page_count(self) -> usize {
+        match self {
+            PokedexFormat::TwoPages => 2,
+            PokedexFormat::OnePage => 1,
+        }
+    }
+
+    pub fn entry_size(self) -> usize {
+        match self {
+            PokedexFormat::TwoPages => 36,
+            PokedexFormat::OnePage => 32,
+        }
+    }
+
+    /// Offset of the scale/offset values, after the description pointers and a u16.
+    fn scale_start(self) -> usize {
+        CATEGORY_NAME_SIZE + 4 + self.page_count() * 4 + 2
+    }
+}
+
+/// Which species-indexed dex number table to read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DexOrder {
+    /// Species to Kanto/Hoenn dex number
+    Regional,
+    /// Species to national dex number
+    National,
+    /// Regional dex number to national dex number
+    RegionalToNational,
+}
+
+/// A decoded Pokédex entry.
+/// Height is in decimetres and weight in hectograms, as the game stores them.
+/// The scale/offset values position the Pokémon and trainer in the size comparison screen.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PokedexInfo {
+    pub category: String,
+    pub height: u16,
+    pub weight: u16,
+    pub description: Vec<String>,
+    pub pokemon_scale: u16,
+    pub pokemon_offset: u16,
+    pub trainer_scale: u16,
+    pub trainer_offset: u16,
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+/// Pointers to the description pages of an entry.
+pub fn description_ptrs(entry: &[u8], format: PokedexFormat) -> Vec<u32> {
+    (0..format.page_count())
+        .map(|page| u32_at(entry, CATEGORY_NAME_SIZE + 4 + page * 4))
+        .collect()
+}
+
+/// Decodes an entry, following its description pointers into `data`.
+pub fn decode_dex_entry(data: &[u8], entry: &[u8], format: PokedexFormat) -> Result<PokedexInfo> {
+    if entry.len() < format.entry_size() {
+        bail!("Pokédex entry too short");
+    }
+    let description = description_ptrs(entry, format)
+        .into_iter()
+        .map(|ptr| match ptr {
+            0 => Ok(String::new()),
+            _ => read_text(data, (ptr & 0x01FFFFFF) as usize),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let scale = format.scale_start();
+    Ok(PokedexInfo {
+        category: decode_text(&entry[..CATEGORY_NAME_SIZE]),
+        height: u16_at(entry, CATEGORY_NAME_SIZE),
+        weight: u16_at(entry, CATEGORY_NAME_SIZE + 2),
+        description,
+        pokemon_scale: u16_at(entry, scale),
+        pokemon_offset: u16_at(entry, scale + 2),
+        trainer_scale: u16_at(entry, scale + 4),
+        trainer_offset: u16_at(entry, scale + 6),
+    })
+}
+
+/// Encodes an entry whose description pages are stored at `description_ptrs`.
+pub fn encode_dex_entry(
+    info: &PokedexInfo,
+    format: PokedexFormat,
+    description_ptrs: &[u32],
+) -> Result<Vec<u8>> {
+    if description_ptrs.len() != format.page_count() {
+        bail!(
+            "Pokédex entries have {} description pages",
+            format.page_count()
+        );
+    }
+
+    let mut bytes = encode_fixed_text(&info.category, CATEGORY_NAME_SIZE)?;
+    bytes.extend(info.height.to_le_bytes());
+    bytes.extend(info.weight.to_le_bytes());
+    for ptr in description_ptrs {
+        bytes.extend(ptr.to_le_bytes());
+    }
+    bytes.extend([0, 0]);
+    for value in [
+        info.pokemon_scale,
+        info.pokemon_offset,
+        info.trainer_scale,
+        info.trainer_offset,
+    ] {
+        bytes.extend(value.to_le_bytes());
+    }
+    bytes.resize(format.entry_size(), 0);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::encode_text;
+
+    #[test]
+    fn test_entry_round_trip() {
+        let info = PokedexInfo {
+            category: "SEED".to_string(),
+            height: 7,
+            weight: 69,
+            description: vec!["A strange seed.".to_string()],
+            pokemon_scale: 356,
+            pokemon_offset: 17,
+            trainer_scale: 256,
+            trainer_offset: 0,
+        };
+        let mut data = vec![0u8; 0x40];
+        data.extend(encode_text(&info.description[0]).unwrap());
+
+        let entry = encode_dex_entry(&info, PokedexFormat::OnePage, &[0x08000040]).unwrap();
+        assert_eq!(entry.len(), 32);
+        let decoded = decode_dex_entry(&data, &entry, PokedexFormat::OnePage).unwrap();
+        assert_eq!(decoded, info);
+        assert!(encode_dex_entry(&info, PokedexFormat::TwoPages, &[0x08000040]).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use crate::checksum::crc32;
+use crate::structures::RomHeader;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Game {
+    Ruby,
+    Sapphire,
+    FireRed,
+    LeafGreen,
+    Emerald,
+}
+
+impl Game {
+    pub fn from_game_code(game_code: &str) -> Option<Self> {
+        match game_code {
+            "AXVE" => Some(Game::Ruby),
+            "AXPE" => Some(Game::Sapphire),
+            "BPRE" => Some(Game::FireRed),
+            "BPGE" => Some(Game::LeafGreen),
+            "BPEE" => Some(Game::Emerald),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Game::Ruby => "Ruby",
+            Game::Sapphire => "Sapphire",
+            Game::FireRed => "FireRed",
+            Game::LeafGreen => "LeafGreen",
+            Game::Emerald => "Emerald",
+        }
+    }
+}
+
+/// CRC32 of clean dumps: game code, revision, CRC32.
+const KNOWN_DUMPS: &[(&str, u8, u32)] = &[
+    ("AXVE", 0, 0xF0815EE7),
+    ("AXVE", 1, 0x61641576),
+    ("AXVE", 2, 0xAEAC73E6),
+    ("AXPE", 0, 0x554DEDC4),
+    ("BPRE", 0, 0xDD88761C),
+    ("BPRE", 1, 0x84EE4776),
+    ("BPGE", 0, 0xD69C96CC),
+    ("BPGE", 1, 0xDAFFECEC),
+    ("BPEE", 0, 0x1F1C08FB),
+];
+
+/// The game and revision a ROM was built from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameProfile {
+    pub game_code: String,
+    pub game: Game,
+    /// 0 for 1.0, 1 for 1.1, ...
+    pub revision: u8,
+    pub crc32: u32,
+    /// Whether the CRC32 matches a known clean dump.
+    pub clean_dump: bool,
+}
+
+impl GameProfile {
+    /// Identifies a ROM from its header and checksum.
+    /// Clean dumps are recognised by CRC32; modified ROMs fall back to the header's
+    /// game code and software version.
+    pub fn detect(header: &RomHeader, data: &[u8]) -> Result<Self> {
+        let Some(game) = Game::from_game_code(&header.game_code) else {
+            bail!("Unsupported game code {}", header.game_code);
+        };
+        let crc32 = crc32(data);
+        let known = KNOWN_DUMPS
+            .iter()
+            .find(|(code, _, crc)| *code == header.game_code && *crc == crc32);
+
+        Ok(Self {
+            game_code: header.game_code.clone(),
+            game,
+            revision: known.map_or(header.version, |(_, revision, _)| *revision),
+            crc32,
+            clean_dump: known.is_some(),
+        })
+    }
+
+    /// e.g. "FireRed 1.1"
+    pub fn name(&self) -> String {
+        format!("{} 1.{}", self.game.name(), self.revision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use binrw::BinRead;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_detect_modified_rom() {
+        let mut rom = vec![0u8; 0x200];
+        rom[0xAC..0xB0].copy_from_slice(b"BPGE");
+        rom[0xBC] = 1;
+        let header = RomHeader::read(&mut Cursor::new(&rom)).unwrap();
+
+        let profile = GameProfile::detect(&header, &rom).unwrap();
+        assert_eq!(profile.game, Game::LeafGreen);
+        assert_eq!(profile.revision, 1);
+        assert!(!profile.clean_dump);
+        assert_eq!(profile.name(), "LeafGreen 1.1");
+
+        rom[0xAC..0xB0].copy_from_slice(b"ZZZZ");
+        let header = RomHeader::read(&mut Cursor::new(&rom)).unwrap();
+        assert!(GameProfile::detect(&header, &rom).is_err());
+    }
+}
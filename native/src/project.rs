@@ -0,0 +1,334 @@
+use crate::history::RecordedChange;
+use crate::modifications::Allocation;
+use crate::offsets::GameOffsets;
+use crate::state::RomState;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Bumped when the project format changes incompatibly.
+pub const PROJECT_VERSION: u32 = 2;
+
+/// Description of the edit standing in for the edits older than the undo history.
+const EARLIER_CHANGES: &str = "Earlier changes";
+
+/// A name for a ROM offset, e.g. a script or table the hack adds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Label {
+    pub offset: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectMetadata {
+    pub name: String,
+    pub notes: String,
+    pub labels: Vec<Label>,
+}
+
+/// Identifies the clean ROM a project is applied to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BaseRom {
+    pub game_code: String,
+    pub revision: u8,
+    pub size: u32,
+    pub crc32: u32,
+}
+
+/// One change made by an edit. Written bytes are in hex, so project files diff well
+/// in version control.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProjectChange {
+    Write {
+        offset: u32,
+        data: String,
+    },
+    /// Discards the pending bytes of a range, restoring the base ROM there
+    Revert {
+        offset: u32,
+        len: u32,
+    },
+}
+
+/// A recorded edit, replayed as one change-set when the project is opened.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectEdit {
+    pub description: String,
+    pub changes: Vec<ProjectChange>,
+    /// Table offsets after the edit, if it changed them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offsets: Option<GameOffsets>,
+}
+
+/// Everything needed to rebuild a hack from the clean ROM, without the ROM itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Project {
+    pub version: u32,
+    pub base_rom: BaseRom,
+    /// Table offsets in use; they differ from the offset database once tables are moved
+    pub offsets: Option<GameOffsets>,
+    /// The edits on the undo stack, oldest first. Edits whose history was dropped come
+    /// first as one "Earlier changes" edit holding the pending bytes they left.
+    /// The redo stack is not saved.
+    pub modifications: Vec<ProjectEdit>,
+    pub allocations: Vec<Allocation>,
+    pub metadata: ProjectMetadata,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+fn from_hex(text: &str) -> Result<Vec<u8>> {
+    text.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let digits = std::str::from_utf8(pair)
+                .ok()
+                .filter(|digits| digits.len() == 2)
+                .context("Invalid hex data")?;
+            u8::from_str_radix(digits, 16).with_context(|| format!("Invalid hex byte {}", digits))
+        })
+        .collect()
+}
+
+fn project_changes(change: &RecordedChange) -> Vec<ProjectChange> {
+    if change.after.is_empty() {
+        return vec![ProjectChange::Revert {
+            offset: change.offset,
+            len: change.len,
+        }];
+    }
+    change
+        .after
+        .iter()
+        .map(|write| ProjectChange::Write {
+            offset: write.offset,
+            data: to_hex(&write.bytes),
+        })
+        .collect()
+}
+
+fn recorded_edits(state: &RomState) -> Vec<ProjectEdit> {
+    let sets = state.history.undo_sets();
+    let earlier = state.pending_before_history();
+
+    let mut edits = Vec::with_capacity(sets.len() + 1);
+    if !earlier.is_empty() {
+        edits.push(ProjectEdit {
+            description: EARLIER_CHANGES.to_string(),
+            changes: earlier
+                .iter()
+                .map(|(offset, bytes)| ProjectChange::Write {
+                    offset,
+                    data: to_hex(bytes),
+                })
+                .collect(),
+            offsets: sets
+                .first()
+                .map_or(&state.offsets, |set| &set.offsets_before)
+                .clone(),
+        });
+    }
+    edits.extend(sets.iter().map(|set| ProjectEdit {
+        description: set.description.clone(),
+        changes: set.changes.iter().flat_map(project_changes).collect(),
+        offsets: if set.offsets_after != set.offsets_before {
+            set.offsets_after.clone()
+        } else {
+            None
+        },
+    }));
+    edits
+}
+
+impl Project {
+    pub fn from_state(state: &RomState) -> Self {
+        Self {
+            version: PROJECT_VERSION,
+            base_rom: BaseRom {
+                game_code: state.profile.game_code.clone(),
+                revision: state.profile.revision,
+                size: state.data.len() as u32,
+                crc32: state.profile.crc32,
+            },
+            offsets: state.offsets.clone(),
+            modifications: recorded_edits(state),
+            allocations: state.allocation_ledger(),
+            metadata: state.metadata.clone(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialise project")
+    }
+
+    pub fn from_json(text: &str) -> Result<Self> {
+        let value: serde_json::Value =
+            serde_json::from_str(text).context("Failed to parse project file")?;
+        let version = value
+            .get("version")
+            .and_then(|version| version.as_u64())
+            .context("Project file has no format version")?;
+        if version > PROJECT_VERSION as u64 {
+            bail!(
+                "Project format version {} is newer than this version of gbaforge supports ({})",
+                version,
+                PROJECT_VERSION
+            );
+        }
+        if version < PROJECT_VERSION as u64 {
+            bail!(
+                "Project format version {} is no longer supported; this version of gbaforge reads version {}",
+                version,
+                PROJECT_VERSION
+            );
+        }
+        serde_json::from_value(value).context("Failed to parse project file")
+    }
+
+    /// Applies the project to a freshly loaded ROM, which must be the clean base ROM.
+    /// The edits are replayed in order, so they can be undone as before the project was saved.
+    pub fn restore(self, state: &mut RomState) -> Result<()> {
+        let base = &self.base_rom;
+        if state.profile.game_code != base.game_code
+            || state.data.len() as u32 != base.size
+            || state.profile.crc32 != base.crc32
+        {
+            bail!(
+                "Project was made for {} ({} bytes, CRC32 {:08X}), but this ROM is {} ({} bytes, CRC32 {:08X})",
+                base.game_code,
+                base.size,
+                base.crc32,
+                state.profile.game_code,
+                state.data.len(),
+                state.profile.crc32
+            );
+        }
+
+        for edit in &self.modifications {
+            state
+                .transaction(&edit.description, |state| {
+                    for change in &edit.changes {
+                        match change {
+                            ProjectChange::Write { offset, data } => {
+                                let bytes = from_hex(data).with_context(|| {
+                                    format!("Bad modification at {:08x}", offset)
+                                })?;
+                                state.apply_patch(*offset, bytes)?;
+                            }
+                            ProjectChange::Revert { offset, len } => state.revert(*offset, *len)?,
+                        }
+                    }
+                    if edit.offsets.is_some() {
+                        state.offsets = edit.offsets.clone();
+                    }
+                    Ok(())
+                })
+                .with_context(|| format!("Failed to replay \"{}\"", edit.description))?;
+        }
+        if self.offsets.is_some() {
+            state.offsets = self.offsets;
+        }
+        state.allocations = self.allocations;
+        state.metadata = self.metadata;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::test_state;
+
+    #[test]
+    fn test_project_round_trip() {
+        let mut state = test_state(b"BPRE", 0x800000, 0xFF);
+        state.apply_patch(0x100, vec![0xAB, 0xCD, 0xEF]).unwrap();
+        state.revert(0x102, 1).unwrap();
+        state
+            .transaction("Move table", |state| {
+                let offset = state.allocate(4)?;
+                state.apply_patch(offset, vec![1, 2, 3, 4])?;
+                Ok(())
+            })
+            .unwrap();
+        state
+            .transaction("Abandoned", |state| {
+                state.allocate(8)?;
+                Err::<(), _>(anyhow::anyhow!("failed"))
+            })
+            .unwrap_err();
+        state.metadata.labels.push(Label {
+            offset: 0x08000100,
+            name: "intro_script".to_string(),
+        });
+
+        let project = Project::from_state(&state);
+        let descriptions: Vec<&str> = project
+            .modifications
+            .iter()
+            .map(|edit| edit.description.as_str())
+            .collect();
+        assert_eq!(
+            descriptions,
+            [
+                "Write at 00000100",
+                "Revert 00000102-00000103",
+                "Move table"
+            ]
+        );
+        assert_eq!(
+            project.modifications[0].changes,
+            [ProjectChange::Write {
+                offset: 0x100,
+                data: "ABCDEF".to_string()
+            }]
+        );
+        assert_eq!(project.allocations.len(), 1);
+        assert_eq!(project.allocations[0].description, "Move table");
+        let json = project.to_json().unwrap();
+
+        let mut reopened = test_state(b"BPRE", 0x800000, 0xFF);
+        Project::from_json(&json)
+            .unwrap()
+            .restore(&mut reopened)
+            .unwrap();
+        assert_eq!(reopened.view(), state.view());
+        assert_eq!(reopened.allocation_ledger(), state.allocation_ledger());
+        assert_eq!(reopened.metadata, state.metadata);
+        assert_eq!(reopened.history_info(), state.history_info());
+
+        // The replayed edits undo one by one
+        reopened.undo().unwrap();
+        reopened.undo().unwrap();
+        assert_eq!(&reopened.view()[0x100..0x103], &[0xAB, 0xCD, 0xEF]);
+
+        // Undoing the move drops its allocation from the ledger
+        state.undo().unwrap();
+        assert!(state.allocation_ledger().is_empty());
+
+        // A different base ROM is rejected
+        let mut other = test_state(b"BPRE", 0x800000, 0x00);
+        assert!(Project::from_json(&json)
+            .unwrap()
+            .restore(&mut other)
+            .is_err());
+    }
+
+    #[test]
+    fn test_project_keeps_edits_older_than_history() {
+        let mut state = test_state(b"BPRE", 0x800000, 0xFF);
+        for i in 0..210u32 {
+            state.apply_patch(0x100 + i * 2, vec![i as u8]).unwrap();
+        }
+
+        let project = Project::from_state(&state);
+        assert_eq!(project.modifications.len(), 201);
+        assert_eq!(project.modifications[0].description, EARLIER_CHANGES);
+        assert_eq!(project.modifications[0].changes.len(), 10);
+
+        let mut reopened = test_state(b"BPRE", 0x800000, 0xFF);
+        project.restore(&mut reopened).unwrap();
+        assert_eq!(reopened.view(), state.view());
+    }
+}
@@ -0,0 +1,85 @@
+use crate::modifications::ModificationStore;
+use anyhow::{bail, Result};
+
+/// Read access to a ROM with pending modifications laid over its original data.
+/// Bytes written past the end of the original ROM extend it; any gap is 0xFF.
+pub struct RomView<'a> {
+    base: &'a [u8],
+    modifications: &'a ModificationStore,
+}
+
+impl<'a> RomView<'a> {
+    pub fn new(base: &'a [u8], modifications: &'a ModificationStore) -> Self {
+        Self {
+            base,
+            modifications,
+        }
+    }
+
+    /// Size of the ROM once the modifications are applied.
+    pub fn len(&self) -> usize {
+        self.modifications
+            .iter()
+            .map(|(offset, bytes)| offset as usize + bytes.len())
+            .fold(self.base.len(), usize::max)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reads `len` bytes at `offset`, taking every modification that covers
+    /// part of the range into account.
+    pub fn read(&self, offset: usize, len: usize) -> Result<Vec<u8>> {
+        let end = offset + len;
+        if end > self.len() {
+            bail!("Read of {} bytes at {:08x} is out of bounds", len, offset);
+        }
+
+        let mut bytes = vec![0xFF; len];
+        if offset < self.base.len() {
+            let base_end = end.min(self.base.len());
+            bytes[..base_end - offset].copy_from_slice(&self.base[offset..base_end]);
+        }
+
+        // Patches starting before the range can still reach into it
+        for (start, patch) in self.modifications.starting_before(end as u32) {
+            let start = start as usize;
+            let patch_end = start + patch.len();
+            if patch_end <= offset {
+                continue;
+            }
+            let from = start.max(offset);
+            let to = patch_end.min(end);
+            bytes[from - offset..to - offset].copy_from_slice(&patch[from - start..to - start]);
+        }
+        Ok(bytes)
+    }
+
+    /// The whole ROM with the modifications applied.
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.read(0, self.len()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_across_patches() {
+        let base = vec![0u8; 16];
+        let mut modifications = ModificationStore::new();
+        modifications.write(2, vec![1, 1, 1, 1]);
+        modifications.write(4, vec![2, 2, 2, 2]);
+        modifications.write(18, vec![3, 3]);
+
+        let view = RomView::new(&base, &modifications);
+        assert_eq!(view.len(), 20);
+        assert_eq!(view.read(3, 4).unwrap(), vec![1, 2, 2, 2]);
+        assert_eq!(view.read(7, 2).unwrap(), vec![2, 0]);
+        // Past the original end: gap of free space, then the appended patch
+        assert_eq!(view.read(15, 5).unwrap(), vec![0, 0xFF, 0xFF, 3, 3]);
+        assert!(view.read(19, 2).is_err());
+    }
+}
@@ -27,6 +27,45 @@ pub enum ScriptCommand {
     Unknown(u8, Vec<u8>), // Opcode + Params
 }
 
+impl ScriptCommand {
+    /// Formats the command as XSE-style source.
+    /// `item_name` resolves item ids to the constant written instead of the raw id.
+    pub fn to_source(&self, item_name: &dyn Fn(u16) -> Option<String>) -> String {
+        match self {
+            ScriptCommand::End => "end".to_string(),
+            ScriptCommand::Return => "return".to_string(),
+            ScriptCommand::Call(ptr) => format!("call 0x{:08X}", ptr),
+            ScriptCommand::Goto(ptr) => format!("goto 0x{:08X}", ptr),
+            ScriptCommand::If1(ptr) => format!("if1 0x{:08X}", ptr),
+            ScriptCommand::If2(ptr) => format!("if2 0x{:08X}", ptr),
+            ScriptCommand::Message { text_ptr, type_id } => {
+                format!("msgbox 0x{:08X} 0x{:X}", text_ptr, type_id)
+            }
+            ScriptCommand::GiveItem { item_id, quantity } => {
+                let item = item_name(*item_id).unwrap_or_else(|| format!("0x{:X}", item_id));
+                format!("giveitem {} 0x{:X}", item, quantity)
+            }
+            ScriptCommand::TrainerBattle {
+                type_id,
+                trainer_id,
+                unk,
+                ptr_intro,
+                ptr_win,
+            } => format!(
+                "trainerbattle 0x{:X} 0x{:X} 0x{:X} 0x{:08X} 0x{:08X}",
+                type_id, trainer_id, unk, ptr_intro, ptr_win
+            ),
+            ScriptCommand::Unknown(opcode, params) => {
+                let mut line = format!("#raw 0x{:02X}", opcode);
+                for byte in params {
+                    line.push_str(&format!(" 0x{:02X}", byte));
+                }
+                line
+            }
+        }
+    }
+}
+
 /// Simple XSE Disassembler
 /// Parsing GBA Scripting bytecode at `offset`.
 pub fn disassemble(data: &[u8], start_offset: usize) -> Result<Vec<ScriptCommand>> {
@@ -168,4 +207,15 @@ mod tests {
             _ => panic!("Expected End"),
         }
     }
+
+    #[test]
+    fn test_to_source_substitutes_item_names() {
+        let command = ScriptCommand::GiveItem {
+            item_id: 0x0D,
+            quantity: 1,
+        };
+        let names = |id: u16| (id == 0x0D).then(|| "ITEM_POTION".to_string());
+        assert_eq!(command.to_source(&names), "giveitem ITEM_POTION 0x1");
+        assert_eq!(command.to_source(&|_| None), "giveitem 0xD 0x1");
+    }
 }
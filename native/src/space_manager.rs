@@ -7,39 +7,63 @@ pub struct SpaceManager {
 }
 
 impl SpaceManager {
-    /// Scans the ROM data for a contiguous block of `needed_size` bytes of `0xFF`.
-    /// Returns the offset of the start of the block.
-    /// `search_start` is usually where the ROM ends (0x800000 + used size) or a safe offset (0x700000).
-    /// For FireRed, typically free space begins after the data.
-    /// Safest approach: Start searching from 0x720000 or allow user config.
-    /// Here we scan from a safe default.
+    /// Scans the ROM data for a block of `needed_size` bytes of `0xFF`.
+    /// Returns the offset of the start of the block, aligned to 4 bytes.
+    /// The block always follows at least one `0xFF` byte, so data ending in a
+    /// `0xFF` terminator (text, learnsets) is never overwritten by the next allocation.
     pub fn find_free_space(data: &[u8], needed_size: usize, search_start: usize) -> Result<usize> {
-        let mut consecutive_ff = 0;
-        let mut start_index = 0;
-
         // Ensure we don't go out of bounds
         if search_start >= data.len() {
              bail!("Search start index out of bounds");
         }
 
-        // Align search to 4 bytes (word alignment) is often good practice for GBA
-        let aligned_start = (search_start + 3) & !3;
-
-        for (i, &byte) in data.iter().enumerate().skip(aligned_start) {
-            if byte == 0xFF {
-                if consecutive_ff == 0 {
-                    start_index = i;
-                }
-                consecutive_ff += 1;
-                
-                if consecutive_ff >= needed_size {
-                    return Ok(start_index);
-                }
-            } else {
-                consecutive_ff = 0;
+        let mut i = search_start;
+        while i < data.len() {
+            if data[i] != 0xFF {
+                i += 1;
+                continue;
+            }
+            let run_start = i;
+            while i < data.len() && data[i] == 0xFF {
+                i += 1;
+            }
+            // Leave one byte of padding after the previous data, then word-align
+            let start = (run_start + 1 + 3) & !3;
+            if start + needed_size <= i {
+                return Ok(start);
             }
         }
 
         bail!("Not enough free space found for {} bytes", needed_size);
     }
+
+    /// Finds every word-aligned pointer to `offset` (as `0x08xxxxxx`) in the ROM.
+    /// Used for repointing: each returned location must be rewritten when the data moves.
+    pub fn find_pointer_refs(data: &[u8], offset: usize) -> Vec<usize> {
+        let target = (0x08000000 | offset as u32).to_le_bytes();
+        data.chunks_exact(4)
+            .enumerate()
+            .filter(|(_, word)| *word == target)
+            .map(|(i, _)| i * 4)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_free_space_is_padded_and_aligned() {
+        // Text ending with its 0xFF terminator at 0x12, free space after it
+        let mut data = vec![0xFFu8; 0x40];
+        data[0x00..0x10].fill(0);
+        data[0x10..0x12].copy_from_slice(&[0xBB, 0xCC]);
+        assert_eq!(SpaceManager::find_free_space(&data, 8, 0).unwrap(), 0x14);
+
+        // A run too short once padded and aligned is skipped
+        data[0x1A] = 0;
+        assert_eq!(SpaceManager::find_free_space(&data, 8, 0).unwrap(), 0x1C);
+        assert!(SpaceManager::find_free_space(&data, 0x40, 0).is_err());
+    }
 }
@@ -0,0 +1,142 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+pub const EVOLUTIONS_PER_SPECIES: usize = 5;
+pub const EVOLUTION_SIZE: usize = 8;
+/// Terminator of a level-up learnset.
+pub const LEARNSET_END: u16 = 0xFFFF;
+/// Safety limit when walking a learnset looking for its terminator.
+const MAX_LEARNSET_LENGTH: usize = 64;
+
+/// One evolution of a species. `param` is a level, item or other value depending on `method`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Evolution {
+    pub method: u16,
+    pub param: u16,
+    pub target_species: u16,
+}
+
+/// Decodes a species' evolution slots, skipping unused ones (method 0).
+pub fn decode_evolutions(bytes: &[u8]) -> Vec<Evolution> {
+    bytes
+        .chunks_exact(EVOLUTION_SIZE)
+        .take(EVOLUTIONS_PER_SPECIES)
+        .map(|e| Evolution {
+            method: u16::from_le_bytes([e[0], e[1]]),
+            param: u16::from_le_bytes([e[2], e[3]]),
+            target_species: u16::from_le_bytes([e[4], e[5]]),
+        })
+        .filter(|e| e.method != 0)
+        .collect()
+}
+
+/// Encodes all 5 evolution slots of a species, clearing the unused ones.
+pub fn encode_evolutions(evolutions: &[Evolution]) -> Result<Vec<u8>> {
+    if evolutions.len() > EVOLUTIONS_PER_SPECIES {
+        bail!(
+            "A species has at most {} evolutions",
+            EVOLUTIONS_PER_SPECIES
+        );
+    }
+    let mut bytes = Vec::with_capacity(EVOLUTIONS_PER_SPECIES * EVOLUTION_SIZE);
+    for evolution in evolutions {
+        bytes.extend(evolution.method.to_le_bytes());
+        bytes.extend(evolution.param.to_le_bytes());
+        bytes.extend(evolution.target_species.to_le_bytes());
+        bytes.extend([0, 0]);
+    }
+    bytes.resize(EVOLUTIONS_PER_SPECIES * EVOLUTION_SIZE, 0);
+    Ok(bytes)
+}
+
+/// A move learnt on level-up.
+/// Stored as a u16: `LLLL LLLM MMMM MMMM` (level: 7 bits, move: 9 bits)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LevelUpMove {
+    pub level: u8,
+    pub move_id: u16,
+}
+
+impl LevelUpMove {
+    pub fn from_raw(raw: u16) -> Self {
+        Self {
+            level: (raw >> 9) as u8,
+            move_id: raw & 0x1FF,
+        }
+    }
+
+    pub fn to_raw(&self) -> Result<u16> {
+        if self.level > 0x7F || self.move_id > 0x1FF {
+            bail!(
+                "Level {} / move {} does not fit the packed learnset format",
+                self.level,
+                self.move_id
+            );
+        }
+        Ok(((self.level as u16) << 9) | self.move_id)
+    }
+}
+
+/// Decodes the learnset at `offset`, up to its 0xFFFF terminator.
+pub fn read_learnset(data: &[u8], offset: usize) -> Result<Vec<LevelUpMove>> {
+    let mut moves = Vec::new();
+    for i in 0..MAX_LEARNSET_LENGTH {
+        let Some(b) = data.get(offset + i * 2..offset + i * 2 + 2) else {
+            bail!("Learnset out of bounds");
+        };
+        let raw = u16::from_le_bytes([b[0], b[1]]);
+        if raw == LEARNSET_END {
+            return Ok(moves);
+        }
+        moves.push(LevelUpMove::from_raw(raw));
+    }
+    bail!("Learnset has no terminator");
+}
+
+/// Encodes a learnset including its terminator.
+pub fn encode_learnset(moves: &[LevelUpMove]) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity((moves.len() + 1) * 2);
+    for m in moves {
+        bytes.extend(m.to_raw()?.to_le_bytes());
+    }
+    bytes.extend(LEARNSET_END.to_le_bytes());
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_learnset_round_trip() {
+        // Tackle (33) at level 1, Vine Whip (22) at level 10
+        let bytes = vec![0x21, 0x02, 0x16, 0x14, 0xFF, 0xFF];
+        let moves = read_learnset(&bytes, 0).unwrap();
+        assert_eq!(
+            moves,
+            vec![
+                LevelUpMove {
+                    level: 1,
+                    move_id: 33
+                },
+                LevelUpMove {
+                    level: 10,
+                    move_id: 22
+                },
+            ]
+        );
+        assert_eq!(encode_learnset(&moves).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_evolutions_padded_to_five() {
+        let evolutions = vec![Evolution {
+            method: 4,
+            param: 16,
+            target_species: 2,
+        }];
+        let bytes = encode_evolutions(&evolutions).unwrap();
+        assert_eq!(bytes.len(), 40);
+        assert_eq!(decode_evolutions(&bytes), evolutions);
+    }
+}
@@ -0,0 +1,328 @@
+use crate::compression::decompress_lz77;
+use crate::graphics::{decode_4bpp_tile, encode_4bpp_tile, rgba_to_bgr555};
+use anyhow::{bail, Result};
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// Size of a sprite or palette table entry: pointer, then a size/tag pair.
+pub const SPRITE_ENTRY_SIZE: usize = 8;
+/// Front and back sprites are 64x64, 8 tiles wide.
+pub const SPRITE_WIDTH_TILES: u32 = 8;
+/// Menu icons are two 32x32 frames stacked vertically.
+pub const ICON_WIDTH_TILES: u32 = 4;
+pub const ICON_SIZE: usize = 32 * 64 / 2;
+/// Footprints are 16x16 at 1 bit per pixel, stored as four 8x8 tiles.
+pub const FOOTPRINT_SIZE: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpriteKind {
+    Front,
+    Back,
+    ShinyFront,
+    ShinyBack,
+}
+
+impl SpriteKind {
+    pub fn is_front(self) -> bool {
+        matches!(self, SpriteKind::Front | SpriteKind::ShinyFront)
+    }
+
+    pub fn is_shiny(self) -> bool {
+        matches!(self, SpriteKind::ShinyFront | SpriteKind::ShinyBack)
+    }
+}
+
+fn data_at(data: &[u8], ptr: u32) -> Result<&[u8]> {
+    let offset = (ptr & 0x01FFFFFF) as usize;
+    if offset >= data.len() {
+        bail!("Graphics pointer {:08x} out of bounds", ptr);
+    }
+    Ok(&data[offset..])
+}
+
+/// Decompresses the graphics or palette a table entry points to.
+pub fn read_compressed(data: &[u8], ptr: u32) -> Result<Vec<u8>> {
+    decompress_lz77(data_at(data, ptr)?)
+}
+
+/// Splits palette bytes into BGR555 colours.
+pub fn palette_colors(bytes: &[u8]) -> Vec<u16> {
+    bytes
+        .chunks_exact(2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .collect()
+}
+
+/// Reads an uncompressed 16-colour palette.
+pub fn read_palette(data: &[u8], ptr: u32) -> Result<Vec<u16>> {
+    let bytes = data_at(data, ptr)?;
+    if bytes.len() < 32 {
+        bail!("Palette at {:08x} out of bounds", ptr);
+    }
+    Ok(palette_colors(&bytes[..32]))
+}
+
+/// Lays out 4bpp tiles row by row into an image `width_tiles` tiles wide.
+/// Colour 0 is transparent.
+pub fn tiles_to_image(tiles: &[u8], palette: &[u16], width_tiles: u32) -> RgbaImage {
+    let tile_count = (tiles.len() / 32) as u32;
+    let height_tiles = tile_count.div_ceil(width_tiles).max(1);
+    let mut image = RgbaImage::new(width_tiles * 8, height_tiles * 8);
+
+    for (i, tile) in tiles.chunks_exact(32).enumerate() {
+        let x = (i as u32 % width_tiles) * 8;
+        let y = (i as u32 / width_tiles) * 8;
+        let tile_image = decode_4bpp_tile(tile, palette);
+        for (tx, ty, pixel) in tile_image.enumerate_pixels() {
+            image.put_pixel(x + tx, y + ty, *pixel);
+        }
+    }
+    image
+}
+
+/// Draws a 1bpp footprint in black on a transparent background.
+pub fn footprint_to_image(bytes: &[u8]) -> Result<RgbaImage> {
+    if bytes.len() < FOOTPRINT_SIZE {
+        bail!("Footprint data too short");
+    }
+    let mut image = RgbaImage::new(16, 16);
+    for (tile, rows) in bytes[..FOOTPRINT_SIZE].chunks_exact(8).enumerate() {
+        let x = (tile as u32 % 2) * 8;
+        let y = (tile as u32 / 2) * 8;
+        for (ty, &row) in rows.iter().enumerate() {
+            for tx in 0..8 {
+                // Leftmost pixel is the lowest bit
+                if row & (1 << tx) != 0 {
+                    image.put_pixel(x + tx, y + ty as u32, Rgba([0, 0, 0, 255]));
+                }
+            }
+        }
+    }
+    Ok(image)
+}
+
+/// Colours available to a sprite besides the transparent colour 0.
+const OPAQUE_COLORS: usize = 15;
+
+/// Sprites quantised to one shared 16-colour palette.
+pub struct QuantizedSprites {
+    /// Colour 0 is the transparent background.
+    pub palette: Vec<u16>,
+    /// Palette index of every pixel, row-major, one list per input image.
+    pub indices: Vec<Vec<u8>>,
+}
+
+/// Colour of each pixel in BGR555; `None` for background pixels.
+/// Pixels with alpha below half are background. Images without transparency use
+/// their top-left pixel's colour as the background, as indexed sprite sheets do.
+fn pixel_colors(image: &RgbaImage) -> Vec<Option<u16>> {
+    let has_alpha = image.pixels().any(|p| p[3] < 0x80);
+    let background = rgba_to_bgr555(image.get_pixel(0, 0).0);
+    image
+        .pixels()
+        .map(|p| {
+            let color = rgba_to_bgr555(p.0);
+            if p[3] < 0x80 || (!has_alpha && color == background) {
+                None
+            } else {
+                Some(color)
+            }
+        })
+        .collect()
+}
+
+fn channels(color: u16) -> [i32; 3] {
+    [
+        (color & 0x1F) as i32,
+        ((color >> 5) & 0x1F) as i32,
+        ((color >> 10) & 0x1F) as i32,
+    ]
+}
+
+/// Reduces weighted colours to at most `max` using median cut.
+fn median_cut(colors: Vec<(u16, usize)>, max: usize) -> Vec<u16> {
+    let mut boxes = vec![colors];
+    while boxes.len() < max {
+        // Split the box with the widest channel range
+        let Some((index, channel, _)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .flat_map(|(i, b)| {
+                (0..3).map(move |c| {
+                    let values = b.iter().map(|&(color, _)| channels(color)[c]);
+                    let range = values.clone().max().unwrap() - values.min().unwrap();
+                    (i, c, range)
+                })
+            })
+            .max_by_key(|&(_, _, range)| range)
+        else {
+            break;
+        };
+
+        let mut split = boxes.swap_remove(index);
+        split.sort_by_key(|&(color, _)| channels(color)[channel]);
+        let upper = split.split_off(split.len() / 2);
+        boxes.push(split);
+        boxes.push(upper);
+    }
+
+    boxes
+        .iter()
+        .map(|b| {
+            let total: usize = b.iter().map(|&(_, n)| n).sum();
+            let mut sum = [0usize; 3];
+            for &(color, n) in b {
+                for (s, c) in sum.iter_mut().zip(channels(color)) {
+                    *s += c as usize * n;
+                }
+            }
+            let [r, g, b] = sum.map(|s| ((s + total / 2) / total) as u16);
+            r | (g << 5) | (b << 10)
+        })
+        .collect()
+}
+
+fn nearest(palette: &[u16], color: u16) -> u8 {
+    let target = channels(color);
+    let distance = |c: u16| {
+        channels(c)
+            .iter()
+            .zip(target)
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum::<i32>()
+    };
+    (0..palette.len())
+        .min_by_key(|&i| distance(palette[i]))
+        .unwrap_or(0) as u8
+}
+
+/// Quantises images to a single palette of 15 colours plus the transparent colour 0,
+/// so a front and back sprite can share one palette.
+pub fn quantize_sprites(images: &[&RgbaImage]) -> QuantizedSprites {
+    let pixels: Vec<Vec<Option<u16>>> = images.iter().map(|i| pixel_colors(i)).collect();
+
+    let mut counts = std::collections::BTreeMap::new();
+    for color in pixels.iter().flatten().flatten() {
+        *counts.entry(*color).or_insert(0usize) += 1;
+    }
+    let colors: Vec<(u16, usize)> = counts.into_iter().collect();
+    let opaque = if colors.len() <= OPAQUE_COLORS {
+        colors.into_iter().map(|(color, _)| color).collect()
+    } else {
+        median_cut(colors, OPAQUE_COLORS)
+    };
+
+    let mut palette = vec![0u16];
+    palette.extend(&opaque);
+    palette.resize(16, 0);
+
+    let indices = pixels
+        .iter()
+        .map(|image| {
+            image
+                .iter()
+                .map(|pixel| match pixel {
+                    Some(color) => 1 + nearest(&opaque, *color),
+                    None => 0,
+                })
+                .collect()
+        })
+        .collect();
+
+    QuantizedSprites { palette, indices }
+}
+
+/// Builds the palette that gives `image` when applied to `indices`.
+/// Used for the shiny palette: the shiny artwork has the same layout as the normal
+/// sprite, so each index takes the colour it has in the shiny image.
+pub fn palette_from_indices(indices: &[u8], image: &RgbaImage, base: &[u16]) -> Result<Vec<u16>> {
+    if indices.len() != (image.width() * image.height()) as usize {
+        bail!("Shiny image does not match the sprite's size");
+    }
+    let mut palette = base.to_vec();
+    for (&index, pixel) in indices.iter().zip(image.pixels()) {
+        if index != 0 {
+            palette[index as usize] = rgba_to_bgr555(pixel.0);
+        }
+    }
+    Ok(palette)
+}
+
+/// Encodes row-major palette indices as 4bpp tiles, row by row.
+pub fn image_to_tiles(indices: &[u8], width: u32) -> Vec<u8> {
+    let width = width as usize;
+    let height = indices.len() / width;
+    let mut tiles = Vec::with_capacity(indices.len() / 2);
+    for ty in 0..height / 8 {
+        for tx in 0..width / 8 {
+            let tile: Vec<u8> = (0..8)
+                .flat_map(|y| {
+                    let row = (ty * 8 + y) * width + tx * 8;
+                    indices[row..row + 8].iter().copied()
+                })
+                .collect();
+            tiles.extend(encode_4bpp_tile(&tile));
+        }
+    }
+    tiles
+}
+
+/// Encodes a palette as little-endian BGR555 bytes.
+pub fn palette_bytes(palette: &[u16]) -> Vec<u8> {
+    palette.iter().flat_map(|c| c.to_le_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_footprint_to_image() {
+        // Top-left tile: first row has the leftmost and rightmost pixels set
+        let mut bytes = vec![0u8; FOOTPRINT_SIZE];
+        bytes[0] = 0x81;
+        // Bottom-right tile: last row, leftmost pixel
+        bytes[31] = 0x01;
+
+        let image = footprint_to_image(&bytes).unwrap();
+        assert_eq!(image.get_pixel(0, 0)[3], 255);
+        assert_eq!(image.get_pixel(7, 0)[3], 255);
+        assert_eq!(image.get_pixel(1, 0)[3], 0);
+        assert_eq!(image.get_pixel(8, 15)[3], 255);
+    }
+
+    #[test]
+    fn test_tiles_to_image_layout() {
+        // Two tiles in a 1-tile-wide strip; the second is solid colour 1
+        let mut tiles = vec![0u8; 64];
+        tiles[32..].fill(0x11);
+        let palette = [0x0000, 0x001F].repeat(8);
+        let image = tiles_to_image(&tiles, &palette, 1);
+        assert_eq!(image.dimensions(), (8, 16));
+        assert_eq!(image.get_pixel(0, 0)[3], 0);
+        assert_eq!(*image.get_pixel(3, 12), Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_quantize_and_encode_round_trip() {
+        // 16x8 image: transparent left half, 20 shades of red on the right
+        let mut image = RgbaImage::new(16, 8);
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            if x >= 8 {
+                *pixel = Rgba([((x - 8) * 8 + y) as u8 * 4, 0, 0, 255]);
+            }
+        }
+
+        let quantized = quantize_sprites(&[&image]);
+        assert_eq!(quantized.palette.len(), 16);
+        assert!(quantized.indices[0].iter().all(|&i| i < 16));
+        assert_eq!(quantized.indices[0][0], 0);
+
+        let tiles = image_to_tiles(&quantized.indices[0], 16);
+        assert_eq!(tiles.len(), 64);
+        let decoded = tiles_to_image(&tiles, &quantized.palette, 2);
+        assert_eq!(decoded.get_pixel(0, 0)[3], 0);
+        assert_eq!(decoded.get_pixel(15, 7)[3], 255);
+    }
+}
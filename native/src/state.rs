@@ -1,24 +1,487 @@
+use crate::history::{History, HistoryInfo, RecordedChange};
+use crate::modifications::{Allocation, ModificationStore, PendingWrite, MAX_ROM_SIZE};
+use crate::offsets::{GameOffsets, OffsetDatabase};
+use crate::profile::GameProfile;
+use crate::project::ProjectMetadata;
+use crate::rom_view::RomView;
+use crate::space_manager::SpaceManager;
 use crate::structures::RomHeader;
+use anyhow::Result;
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
-use std::sync::RwLock;
+use std::sync::{OnceLock, RwLock};
 
 // Global state required for FFI - accessed via RwLock for thread safety
-pub static APP_STATE: Lazy<RwLock<Option<RomState>>> = Lazy::new(|| RwLock::new(None));
+pub static SESSIONS: Lazy<RwLock<Sessions>> = Lazy::new(|| RwLock::new(Sessions::default()));
+
+// Offset database shared by all ROMs; user offset files are merged into it
+pub static OFFSET_DATABASE: Lazy<RwLock<OffsetDatabase>> =
+    Lazy::new(|| RwLock::new(OffsetDatabase::builtin()));
+
+/// Handle of an open ROM, returned by `load_rom` and passed to every api function.
+pub type SessionId = u32;
+
+/// The ROMs open side by side, by session handle.
+#[derive(Default)]
+pub struct Sessions {
+    last: SessionId,
+    open: BTreeMap<SessionId, RomState>,
+}
+
+impl Sessions {
+    /// Registers a loaded ROM and returns its handle. Handles start at 1 and are never reused.
+    pub fn open(&mut self, state: RomState) -> SessionId {
+        self.last += 1;
+        self.open.insert(self.last, state);
+        self.last
+    }
+
+    pub fn close(&mut self, session: SessionId) -> Result<RomState> {
+        self.open
+            .remove(&session)
+            .ok_or_else(|| anyhow::anyhow!("No ROM open in session {}", session))
+    }
+
+    pub fn get(&self, session: SessionId) -> Result<&RomState> {
+        self.open
+            .get(&session)
+            .ok_or_else(|| anyhow::anyhow!("No ROM open in session {}", session))
+    }
+
+    pub fn get_mut(&mut self, session: SessionId) -> Result<&mut RomState> {
+        self.open
+            .get_mut(&session)
+            .ok_or_else(|| anyhow::anyhow!("No ROM open in session {}", session))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (SessionId, &RomState)> {
+        self.open.iter().map(|(&session, state)| (session, state))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (SessionId, &mut RomState)> {
+        self.open
+            .iter_mut()
+            .map(|(&session, state)| (session, state))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub session: SessionId,
+    pub title: String,
+    pub game: String,
+    pub pending_changes: bool,
+}
 
 pub struct RomState {
     pub data: Vec<u8>,
     pub header: RomHeader,
-    // We will Implement TableOffsets later
-    pub modifications: BTreeMap<u32, Vec<u8>>,
+    pub profile: GameProfile,
+    // Pending writes, applied to `data` when the ROM is saved
+    pub modifications: ModificationStore,
+    // Table locations for this ROM; updated when a table is moved
+    pub offsets: Option<GameOffsets>,
+    // Undo/redo stacks of change-sets
+    pub history: History,
+    // Every allocation made, oldest first; see `allocation_ledger`
+    pub allocations: Vec<Allocation>,
+    // Labels and notes saved with the project
+    pub metadata: ProjectMetadata,
+    // `data` with the modifications applied, built on the first read after a write
+    merged: OnceLock<Vec<u8>>,
+}
+
+/// Fails unless `offset..offset + len` lies within the cartridge address space.
+pub fn check_range(offset: u32, len: usize) -> Result<()> {
+    u32::try_from(len)
+        .ok()
+        .and_then(|len| offset.checked_add(len))
+        .filter(|&end| end <= MAX_ROM_SIZE)
+        .map(|_| ())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "{} bytes at {:08x} run past the end of a {} MB ROM",
+                len,
+                offset,
+                MAX_ROM_SIZE >> 20
+            )
+        })
 }
 
 impl RomState {
-    pub fn new(data: Vec<u8>, header: RomHeader) -> Self {
+    /// Creates the state of a loaded ROM, taking its table offsets from the offset database.
+    pub fn new(data: Vec<u8>, header: RomHeader, profile: GameProfile) -> Self {
+        let offsets = OFFSET_DATABASE
+            .read()
+            .ok()
+            .and_then(|db| db.lookup(&profile.game_code, profile.revision));
         Self {
             data,
             header,
-            modifications: BTreeMap::new(),
+            profile,
+            modifications: ModificationStore::new(),
+            offsets,
+            history: History::default(),
+            allocations: Vec::new(),
+            metadata: ProjectMetadata::default(),
+            merged: OnceLock::new(),
+        }
+    }
+
+    pub fn info(&self, session: SessionId) -> SessionInfo {
+        SessionInfo {
+            session,
+            title: self.header.game_title.clone(),
+            game: self.profile.name(),
+            pending_changes: !self.modifications.is_empty(),
+        }
+    }
+
+    pub fn offsets(&self) -> Result<&GameOffsets> {
+        self.offsets.as_ref().ok_or(anyhow::anyhow!(
+            "No table offsets known for {} ({})",
+            self.profile.name(),
+            OffsetDatabase::section_name(&self.profile.game_code, self.profile.revision)
+        ))
+    }
+
+    /// Records a pending write of `bytes` at `offset`; it replaces any pending bytes there.
+    /// Nothing touches `data` until the ROM is saved.
+    /// The write joins the open change-set, or becomes a change-set of its own.
+    /// Writes past the end of the cartridge address space are rejected.
+    /// Returns the pending runs the write partially overlapped, as (offset, length)
+    /// pairs (see `ModificationStore::partial_overlaps`), for callers to reject.
+    pub fn apply_patch(&mut self, offset: u32, bytes: Vec<u8>) -> Result<Vec<(u32, u32)>> {
+        check_range(offset, bytes.len())?;
+        let change = RecordedChange {
+            offset,
+            len: bytes.len() as u32,
+            before: self.pending(offset, bytes.len() as u32),
+            after: vec![PendingWrite {
+                offset,
+                bytes: bytes.clone(),
+            }],
+        };
+        self.record(&format!("Write at {:08x}", offset), change);
+        Ok(self.store_write(offset, bytes))
+    }
+
+    /// Discards pending writes in `offset..offset + len`, restoring the original bytes.
+    pub fn revert(&mut self, offset: u32, len: u32) -> Result<()> {
+        check_range(offset, len as usize)?;
+        let change = RecordedChange {
+            offset,
+            len,
+            before: self.pending(offset, len),
+            after: Vec::new(),
+        };
+        self.record(
+            &format!("Revert {:08x}-{:08x}", offset, offset + len),
+            change,
+        );
+        self.store_revert(offset, len);
+        Ok(())
+    }
+
+    fn record(&mut self, description: &str, change: RecordedChange) {
+        let standalone = !self.history.is_open();
+        if standalone {
+            self.history.begin(description, &self.offsets);
+        }
+        self.history.record(change);
+        if standalone {
+            self.history.end(&self.offsets);
+        }
+    }
+
+    fn store_write(&mut self, offset: u32, bytes: Vec<u8>) -> Vec<(u32, u32)> {
+        if let Some(merged) = self.merged.get_mut() {
+            let end = offset as usize + bytes.len();
+            if end > merged.len() {
+                merged.resize(end, 0xFF);
+            }
+            merged[offset as usize..end].copy_from_slice(&bytes);
+        }
+        self.modifications.write(offset, bytes)
+    }
+
+    fn store_revert(&mut self, offset: u32, len: u32) {
+        self.modifications.revert(offset, len);
+        self.merged.take();
+    }
+
+    /// Puts a range back to the pending bytes `pieces` describe.
+    fn restore(&mut self, offset: u32, len: u32, pieces: &[PendingWrite]) {
+        self.store_revert(offset, len);
+        for piece in pieces {
+            self.store_write(piece.offset, piece.bytes.clone());
+        }
+    }
+
+    /// Runs an editing operation as a named change-set.
+    /// If it fails, everything it changed is rolled back.
+    pub fn transaction<T>(
+        &mut self,
+        description: &str,
+        edit: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        self.history.begin(description, &self.offsets);
+        let mark = self.history.mark();
+        let offsets = self.offsets.clone();
+
+        let result = edit(self);
+        if result.is_err() {
+            for change in self.history.take_since(mark) {
+                self.restore(change.offset, change.len, &change.before);
+            }
+            self.offsets = offsets;
+        }
+        self.history.end(&self.offsets);
+        result
+    }
+
+    /// Starts grouping the following operations into one change-set.
+    pub fn begin_group(&mut self, description: &str) {
+        self.history.begin(description, &self.offsets);
+    }
+
+    pub fn end_group(&mut self) {
+        self.history.end(&self.offsets);
+    }
+
+    /// Undoes the most recent change-set and returns its description.
+    pub fn undo(&mut self) -> Result<Option<String>> {
+        if self.history.is_open() {
+            anyhow::bail!("Cannot undo while a change group is open");
         }
+        let Some(set) = self.history.pop_undo() else {
+            return Ok(None);
+        };
+        for change in set.changes.iter().rev() {
+            self.restore(change.offset, change.len, &change.before);
+        }
+        self.change_offsets(&set.offsets_after, &set.offsets_before)?;
+        let description = set.description.clone();
+        self.history.push_redo(set);
+        Ok(Some(description))
+    }
+
+    /// Redoes the most recently undone change-set and returns its description.
+    pub fn redo(&mut self) -> Result<Option<String>> {
+        if self.history.is_open() {
+            anyhow::bail!("Cannot redo while a change group is open");
+        }
+        let Some(set) = self.history.pop_redo() else {
+            return Ok(None);
+        };
+        for change in &set.changes {
+            self.restore(change.offset, change.len, &change.after);
+        }
+        self.change_offsets(&set.offsets_before, &set.offsets_after)?;
+        let description = set.description.clone();
+        self.history.push_undo(set);
+        Ok(Some(description))
+    }
+
+    /// Replays a change-set's effect on the offsets. Only the fields it changed are
+    /// set, so offsets changed outside the history (a section switch) are kept.
+    fn change_offsets(
+        &mut self,
+        from: &Option<GameOffsets>,
+        to: &Option<GameOffsets>,
+    ) -> Result<()> {
+        match (self.offsets.as_mut(), from, to) {
+            (Some(current), Some(from), Some(to)) => current.apply_changes(from, to),
+            _ => {
+                if from != to {
+                    self.offsets = to.clone();
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub fn history_info(&self) -> HistoryInfo {
+        self.history.info()
+    }
+
+    /// Pending bytes as they were before the oldest change-set that can still be undone:
+    /// the result of the edits whose history was dropped.
+    pub fn pending_before_history(&self) -> ModificationStore {
+        let mut store = self.modifications.clone();
+        for set in self.history.undo_sets().iter().rev() {
+            for change in set.changes.iter().rev() {
+                store.revert(change.offset, change.len);
+                for piece in &change.before {
+                    store.write(piece.offset, piece.bytes.clone());
+                }
+            }
+        }
+        store
+    }
+
+    /// Pending bytes within `offset..offset + len`.
+    pub fn pending(&self, offset: u32, len: u32) -> Vec<PendingWrite> {
+        self.modifications.pending(offset, len)
+    }
+
+    /// Reads through the pending modifications without building the whole ROM.
+    pub fn rom_view(&self) -> RomView<'_> {
+        RomView::new(&self.data, &self.modifications)
+    }
+
+    /// The ROM as the editors should see it: `data` with all pending modifications applied.
+    /// Every reader uses this so an edit is visible before the ROM is saved.
+    pub fn view(&self) -> &[u8] {
+        self.merged.get_or_init(|| self.rom_view().to_vec())
+    }
+
+    /// Finds free space for `size` bytes, taking pending writes into account
+    /// so two allocations made before saving never overlap.
+    /// The allocation is logged under the open change-set's description.
+    pub fn allocate(&mut self, size: usize) -> Result<u32> {
+        let search_start = self.offsets()?.free_space_start;
+        if search_start == 0 {
+            anyhow::bail!(
+                "No free space start known for {}; set free_space_start in its offsets",
+                self.profile.name()
+            );
+        }
+        let offset =
+            SpaceManager::find_free_space(self.view(), size, search_start as usize)? as u32;
+        self.allocations.push(Allocation {
+            offset,
+            size: size as u32,
+            description: self
+                .history
+                .open_description()
+                .unwrap_or_default()
+                .to_string(),
+        });
+        Ok(offset)
+    }
+
+    /// Allocations still in use, by offset.
+    /// An allocation is dropped once its data is no longer pending (the edit was
+    /// undone, rolled back or reverted) or a later allocation reused its space.
+    pub fn allocation_ledger(&self) -> Vec<Allocation> {
+        let mut ledger: Vec<Allocation> = Vec::new();
+        for allocation in self.allocations.iter().rev() {
+            let reused = ledger
+                .iter()
+                .any(|later| later.offset < allocation.end() && allocation.offset < later.end());
+            if !reused && !self.pending(allocation.offset, allocation.size).is_empty() {
+                ledger.push(allocation.clone());
+            }
+        }
+        ledger.sort_by_key(|allocation| allocation.offset);
+        ledger
+    }
+
+    /// Replaces data of `old_size` bytes at `old_offset`.
+    /// The data is overwritten in place when it fits; otherwise it is written to
+    /// free space and the caller must update whatever points to it.
+    /// Returns the offset the data was written to.
+    pub fn write_data(&mut self, old_offset: u32, old_size: usize, bytes: Vec<u8>) -> Result<u32> {
+        let offset = if bytes.len() <= old_size {
+            old_offset
+        } else {
+            self.allocate(bytes.len())?
+        };
+        self.apply_patch(offset, bytes)?;
+        Ok(offset)
+    }
+
+    /// Same as `write_data`, updating the pointer stored at `ptr_location` if the data moved.
+    pub fn write_pointed_data(
+        &mut self,
+        ptr_location: u32,
+        old_offset: u32,
+        old_size: usize,
+        bytes: Vec<u8>,
+    ) -> Result<u32> {
+        let offset = self.write_data(old_offset, old_size, bytes)?;
+        if offset != old_offset {
+            self.apply_patch(ptr_location, (0x08000000 | offset).to_le_bytes().to_vec())?;
+        }
+        Ok(offset)
+    }
+
+    /// Writes `bytes` to newly allocated space and repoints every pointer to `old_offset`.
+    /// The old data is left in place. Returns the new offset.
+    pub fn relocate(&mut self, old_offset: u32, bytes: Vec<u8>) -> Result<u32> {
+        let new_offset = self.allocate(bytes.len())?;
+        let refs = SpaceManager::find_pointer_refs(self.view(), old_offset as usize);
+        self.apply_patch(new_offset, bytes)?;
+        for location in refs {
+            self.apply_patch(
+                location as u32,
+                (0x08000000 | new_offset).to_le_bytes().to_vec(),
+            )?;
+        }
+        Ok(new_offset)
+    }
+}
+
+/// A `size`-byte ROM of `fill` bytes with the header of revision 0 of `code`.
+#[cfg(test)]
+pub(crate) fn test_state(code: &[u8; 4], size: usize, fill: u8) -> RomState {
+    use binrw::BinRead;
+    let mut rom = vec![fill; size];
+    rom[0xAC..0xB0].copy_from_slice(code);
+    rom[0xBC] = 0;
+    let header = RomHeader::read(&mut std::io::Cursor::new(&rom)).unwrap();
+    let profile = GameProfile::detect(&header, &rom).unwrap();
+    RomState::new(rom, header, profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sessions_are_independent() {
+        let mut sessions = Sessions::default();
+        let vanilla = sessions.open(test_state(b"BPRE", 0x200, 0));
+        let hack = sessions.open(test_state(b"BPEE", 0x200, 0));
+        assert_ne!(vanilla, hack);
+
+        sessions
+            .get_mut(hack)
+            .unwrap()
+            .apply_patch(0x100, vec![1])
+            .unwrap();
+        assert_eq!(sessions.get(vanilla).unwrap().view()[0x100], 0);
+        assert_eq!(sessions.get(hack).unwrap().view()[0x100], 1);
+
+        sessions.close(vanilla).unwrap();
+        assert!(sessions.get(vanilla).is_err());
+        // Handles are not reused after closing
+        assert!(sessions.open(test_state(b"BPRE", 0x200, 0)) > hack);
+    }
+
+    #[test]
+    fn test_writes_past_address_space_are_rejected() {
+        let mut state = test_state(b"BPRE", 0x200, 0);
+        assert!(state.apply_patch(0xFFFFFFFF, vec![0; 2]).is_err());
+        assert!(state.apply_patch(MAX_ROM_SIZE - 1, vec![0; 2]).is_err());
+        assert!(state.revert(0xFFFFFFF0, 0x20).is_err());
+        assert!(state.modifications.is_empty());
+        assert!(state.history_info().undo.is_empty());
+        state.apply_patch(MAX_ROM_SIZE - 2, vec![0; 2]).unwrap();
+    }
+
+    #[test]
+    fn test_writes_report_partial_overlaps() {
+        let mut state = test_state(b"BPRE", 0x200, 0);
+        assert!(state.apply_patch(0x100, vec![1; 4]).unwrap().is_empty());
+        // Rewriting inside the run, or covering it, is not a partial overlap
+        assert!(state.apply_patch(0x101, vec![2; 2]).unwrap().is_empty());
+        assert_eq!(
+            state.apply_patch(0x102, vec![3; 4]).unwrap(),
+            vec![(0x100, 4)]
+        );
+        assert_eq!(&state.view()[0x100..0x106], &[1, 2, 3, 3, 3, 3]);
     }
 }
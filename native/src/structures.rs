@@ -1,4 +1,7 @@
 use binrw::{BinRead, BinWrite};
+use serde::{Deserialize, Serialize};
+
+use crate::metatiles::AttributeFormat;
 
 #[derive(BinRead, BinWrite, Debug)]
 #[br(little)]
@@ -37,7 +40,7 @@ pub struct RomHeader {
     pub reserved_2: u16,
 }
 
-#[derive(BinRead, BinWrite, Debug, Clone)]
+#[derive(BinRead, BinWrite, Debug, Clone, Serialize, Deserialize)]
 #[br(little)]
 pub struct MapHeader {
     pub map_data_ptr: u32,
@@ -55,7 +58,7 @@ pub struct MapHeader {
     pub battle_scene: u8,
 }
 
-#[derive(BinRead, BinWrite, Debug, Clone)]
+#[derive(BinRead, BinWrite, Debug, Clone, Serialize, Deserialize)]
 #[br(little)]
 pub struct MapLayout {
     pub width: u32,
@@ -67,7 +70,7 @@ pub struct MapLayout {
     pub border_width: u8,
     pub border_height: u8,
     #[br(count = 2)] // padding
-    pub unused: Vec<u8>, 
+    pub unused: Vec<u8>,
 }
 
 #[derive(BinRead, BinWrite, Debug, Clone)]
@@ -80,6 +83,209 @@ pub struct TilesetHeader {
     pub graphics_ptr: u32,
     pub palette_ptr: u32,
     pub metatiles_ptr: u32,
-    pub anim_ptr: u32,
-    pub behavior_ptr: u32,
+    // Callback in FireRed/LeafGreen, metatile attributes in Ruby/Sapphire/Emerald
+    pub ptr_10: u32,
+    // Metatile attributes in FireRed/LeafGreen, callback in Ruby/Sapphire/Emerald
+    pub ptr_14: u32,
+}
+
+impl TilesetHeader {
+    /// Pointer to the metatile behaviour/background attributes.
+    pub fn attributes_ptr(&self, format: AttributeFormat) -> u32 {
+        match format {
+            AttributeFormat::FireRed => self.ptr_14,
+            AttributeFormat::Emerald => self.ptr_10,
+        }
+    }
+
+    /// Pointer to the callback that installs the tileset's tile animations.
+    pub fn callback_ptr(&self, format: AttributeFormat) -> u32 {
+        match format {
+            AttributeFormat::FireRed => self.ptr_10,
+            AttributeFormat::Emerald => self.ptr_14,
+        }
+    }
+}
+
+// Event counts and lists of a map (20 bytes)
+#[derive(BinRead, BinWrite, Debug, Clone)]
+#[br(little)]
+pub struct MapEvents {
+    pub object_count: u8,
+    pub warp_count: u8,
+    pub coord_event_count: u8,
+    pub bg_event_count: u8,
+    pub object_events_ptr: u32,
+    pub warps_ptr: u32,
+    pub coord_events_ptr: u32,
+    pub bg_events_ptr: u32,
+}
+
+// Person (NPC) event (24 bytes)
+#[derive(BinRead, BinWrite, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[br(little)]
+pub struct ObjectEventTemplate {
+    pub local_id: u8,
+    pub graphics_id: u8,
+    pub kind: u8,
+    pub padding1: u8,
+    pub x: i16,
+    pub y: i16,
+    pub elevation: u8,
+    pub movement_type: u8,
+    // Low nibble x, high nibble y
+    pub movement_range: u8,
+    pub padding2: u8,
+    pub trainer_type: u16,
+    pub trainer_range: u16,
+    pub script_ptr: u32,
+    pub flag_id: u16,
+    pub padding3: u16,
+}
+
+// Overworld sprite template (36 bytes)
+#[derive(BinRead, BinWrite, Debug, Clone)]
+#[br(little)]
+pub struct OverworldGraphicsInfo {
+    pub tile_tag: u16,
+    pub palette_tag: u16,
+    pub reflection_palette_tag: u16,
+    // Bytes of one frame
+    pub size: u16,
+    pub width: i16,
+    pub height: i16,
+    // Bits 0-3 palette slot, 4-5 shadow size, 6 inanimate, 7 no reflection palette
+    pub flags: u8,
+    pub tracks: u8,
+    pub padding: u16,
+    pub oam_ptr: u32,
+    pub subsprite_tables_ptr: u32,
+    pub anims_ptr: u32,
+    pub images_ptr: u32,
+    pub affine_anims_ptr: u32,
+}
+
+// Wild encounter header, one per map with wild Pokémon (20 bytes)
+#[derive(BinRead, BinWrite, Debug, Clone)]
+#[br(little)]
+pub struct WildHeader {
+    pub bank: u8,
+    pub map: u8,
+    pub padding: u16,
+    pub land_ptr: u32,
+    pub water_ptr: u32,
+    pub rock_smash_ptr: u32,
+    pub fishing_ptr: u32,
+}
+
+#[derive(BinRead, BinWrite, Debug, Clone)]
+#[br(little)]
+pub struct WildInfo {
+    pub encounter_rate: u8,
+    #[br(count = 3)]
+    pub padding: Vec<u8>,
+    pub mons_ptr: u32,
+}
+
+#[derive(BinRead, BinWrite, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[br(little)]
+pub struct WildMon {
+    pub min_level: u8,
+    pub max_level: u8,
+    pub species: u16,
+}
+
+// Species base stats (28 bytes)
+#[derive(BinRead, BinWrite, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[br(little)]
+pub struct BaseStats {
+    pub hp: u8,
+    pub attack: u8,
+    pub defense: u8,
+    pub speed: u8,
+    pub sp_attack: u8,
+    pub sp_defense: u8,
+    pub type1: u8,
+    pub type2: u8,
+    pub catch_rate: u8,
+    pub exp_yield: u8,
+    // 2 bits per stat: HP, Atk, Def, Spd, SpAtk, SpDef
+    pub ev_yield: u16,
+    pub item1: u16,
+    pub item2: u16,
+    // 0 = always male, 254 = always female, 255 = genderless
+    pub gender_ratio: u8,
+    pub egg_cycles: u8,
+    pub friendship: u8,
+    pub growth_rate: u8,
+    pub egg_group1: u8,
+    pub egg_group2: u8,
+    pub ability1: u8,
+    pub ability2: u8,
+    pub safari_flee_rate: u8,
+    // Low 7 bits: body colour, high bit: flip sprite
+    pub color_flip: u8,
+    pub padding: u16,
+}
+
+// Battle move data (12 bytes)
+#[derive(BinRead, BinWrite, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[br(little)]
+pub struct BattleMove {
+    pub effect: u8,
+    pub power: u8,
+    pub move_type: u8,
+    pub accuracy: u8,
+    pub pp: u8,
+    pub secondary_effect_chance: u8,
+    pub target: u8,
+    pub priority: i8,
+    // Contact, Protect, Magic Coat, Snatch, Mirror Move, King's Rock
+    pub flags: u8,
+    #[br(count = 3)]
+    pub padding: Vec<u8>,
+}
+
+// Trainer table entry (40 bytes)
+#[derive(BinRead, BinWrite, Debug, Clone)]
+#[br(little)]
+pub struct Trainer {
+    // Bit 0: custom moves, bit 1: held items
+    pub party_flags: u8,
+    pub trainer_class: u8,
+    // Low 7 bits: encounter music, high bit: female
+    pub encounter_music_gender: u8,
+    pub trainer_pic: u8,
+    #[br(count = 12)]
+    pub name: Vec<u8>,
+    #[br(count = 4)]
+    pub items: Vec<u16>,
+    pub double_battle: u32,
+    pub ai_flags: u32,
+    pub party_size: u32,
+    pub party_ptr: u32,
+}
+
+// Item table entry (44 bytes)
+#[derive(BinRead, BinWrite, Debug, Clone)]
+#[br(little)]
+pub struct Item {
+    #[br(count = 14)]
+    pub name: Vec<u8>,
+    pub item_id: u16,
+    pub price: u16,
+    pub hold_effect: u8,
+    pub hold_effect_param: u8,
+    pub description_ptr: u32,
+    pub importance: u8,
+    // FireRed: exits the bag on use, Emerald: can be registered
+    pub registrability: u8,
+    pub pocket: u8,
+    pub item_type: u8,
+    pub field_use_func: u32,
+    pub battle_usage: u8,
+    #[br(count = 3)]
+    pub padding: Vec<u8>,
+    pub battle_use_func: u32,
+    pub secondary_id: u32,
 }
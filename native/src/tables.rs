@@ -0,0 +1,72 @@
+use anyhow::{bail, Context, Result};
+use binrw::{BinRead, BinWrite};
+use std::io::Cursor;
+
+/// Reads a fixed-size binrw table entry at `offset`.
+pub fn read_entry<T>(data: &[u8], offset: u32, entry_size: usize) -> Result<T>
+where
+    T: for<'a> BinRead<Args<'a> = ()>,
+{
+    let start = offset as usize;
+    if start + entry_size > data.len() {
+        bail!("Table entry at {:08x} out of bounds", offset);
+    }
+    let mut reader = Cursor::new(&data[start..start + entry_size]);
+    T::read_le(&mut reader).with_context(|| format!("Failed to read table entry at {:08x}", offset))
+}
+
+/// Encodes a binrw entry for writing back into its table.
+pub fn encode_entry<T>(entry: &T) -> Result<Vec<u8>>
+where
+    T: for<'a> BinWrite<Args<'a> = ()>,
+{
+    let mut writer = Cursor::new(Vec::new());
+    entry
+        .write_le(&mut writer)
+        .context("Failed to encode table entry")?;
+    Ok(writer.into_inner())
+}
+
+/// Offset of entry `index`, checking it against the table length.
+pub fn entry_offset(table_offset: u32, index: u32, entry_size: usize, count: u32) -> Result<u32> {
+    if index >= count {
+        bail!("Index {} out of range (table has {} entries)", index, count);
+    }
+    Ok(table_offset + index * entry_size as u32)
+}
+
+/// Reads a table of `count` u16 values (TM moves, dex numbers).
+pub fn read_u16_table(data: &[u8], offset: u32, count: usize) -> Result<Vec<u16>> {
+    let start = offset as usize;
+    let Some(bytes) = data.get(start..start + count * 2) else {
+        bail!("Table at {:08x} out of bounds", offset);
+    };
+    Ok(bytes
+        .chunks_exact(2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::BaseStats;
+
+    #[test]
+    fn test_base_stats_round_trip() {
+        // Bulbasaur: 45/49/49/45/65/65, Grass/Poison
+        let mut entry = vec![45, 49, 49, 45, 65, 65, 12, 3, 45, 64];
+        entry.extend([
+            0x00, 0x01, 0, 0, 0, 0, 31, 20, 70, 3, 1, 7, 65, 0, 0, 0x03, 0, 0,
+        ]);
+        let mut table = vec![0u8; 28];
+        table.extend(&entry);
+
+        let stats: BaseStats = read_entry(&table, entry_offset(0, 1, 28, 2).unwrap(), 28).unwrap();
+        assert_eq!(stats.sp_attack, 65);
+        assert_eq!(stats.type2, 3);
+        assert_eq!(stats.ev_yield, 0x0100);
+        assert_eq!(stats.ability1, 65);
+        assert_eq!(encode_entry(&stats).unwrap(), entry);
+    }
+}
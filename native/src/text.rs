@@ -0,0 +1,206 @@
+use anyhow::{bail, Result};
+
+/// String terminator.
+pub const EOS: u8 = 0xFF;
+/// Longest string decoded before giving up on finding a terminator.
+pub const MAX_TEXT_LENGTH: usize = 0x400;
+
+/// Western Gen III character set.
+/// Bytes without an entry are written as `[XX]` so every string round-trips.
+const CHARSET: &[(u8, char)] = &[
+    (0x00, ' '),
+    (0x01, 'À'),
+    (0x02, 'Á'),
+    (0x03, 'Â'),
+    (0x04, 'Ç'),
+    (0x05, 'È'),
+    (0x06, 'É'),
+    (0x07, 'Ê'),
+    (0x08, 'Ë'),
+    (0x09, 'Ì'),
+    (0x0B, 'Î'),
+    (0x0C, 'Ï'),
+    (0x0D, 'Ò'),
+    (0x0E, 'Ó'),
+    (0x0F, 'Ô'),
+    (0x10, 'Œ'),
+    (0x11, 'Ù'),
+    (0x12, 'Ú'),
+    (0x13, 'Û'),
+    (0x14, 'Ñ'),
+    (0x15, 'ß'),
+    (0x16, 'à'),
+    (0x17, 'á'),
+    (0x19, 'ç'),
+    (0x1A, 'è'),
+    (0x1B, 'é'),
+    (0x1C, 'ê'),
+    (0x1D, 'ë'),
+    (0x1E, 'ì'),
+    (0x20, 'î'),
+    (0x21, 'ï'),
+    (0x22, 'ò'),
+    (0x23, 'ó'),
+    (0x24, 'ô'),
+    (0x25, 'œ'),
+    (0x26, 'ù'),
+    (0x27, 'ú'),
+    (0x28, 'û'),
+    (0x29, 'ñ'),
+    (0x2A, 'º'),
+    (0x2B, 'ª'),
+    (0x2D, '&'),
+    (0x2E, '+'),
+    (0x35, '='),
+    (0x36, ';'),
+    (0x51, '¿'),
+    (0x52, '¡'),
+    (0x5A, 'Í'),
+    (0x5B, '%'),
+    (0x5C, '('),
+    (0x5D, ')'),
+    (0x68, 'â'),
+    (0x6F, 'í'),
+    (0xAB, '!'),
+    (0xAC, '?'),
+    (0xAD, '.'),
+    (0xAE, '-'),
+    (0xAF, '·'),
+    (0xB0, '…'),
+    (0xB1, '“'),
+    (0xB2, '”'),
+    (0xB3, '‘'),
+    (0xB4, '’'),
+    (0xB5, '♂'),
+    (0xB6, '♀'),
+    (0xB7, '$'),
+    (0xB8, ','),
+    (0xB9, '×'),
+    (0xBA, '/'),
+    (0xEF, '▶'),
+    (0xF0, ':'),
+    (0xF1, 'Ä'),
+    (0xF2, 'Ö'),
+    (0xF3, 'Ü'),
+    (0xF4, 'ä'),
+    (0xF5, 'ö'),
+    (0xF6, 'ü'),
+];
+
+/// Line/paragraph control bytes, written with XSE's escapes.
+const CONTROLS: &[(u8, &str)] = &[(0xFA, "\\l"), (0xFB, "\\p"), (0xFE, "\\n")];
+
+fn decode_char(byte: u8) -> Option<char> {
+    match byte {
+        0xA1..=0xAA => Some((b'0' + (byte - 0xA1)) as char),
+        0xBB..=0xD4 => Some((b'A' + (byte - 0xBB)) as char),
+        0xD5..=0xEE => Some((b'a' + (byte - 0xD5)) as char),
+        _ => CHARSET.iter().find(|(b, _)| *b == byte).map(|(_, c)| *c),
+    }
+}
+
+fn encode_char(c: char) -> Option<u8> {
+    match c {
+        '0'..='9' => Some(0xA1 + (c as u8 - b'0')),
+        'A'..='Z' => Some(0xBB + (c as u8 - b'A')),
+        'a'..='z' => Some(0xD5 + (c as u8 - b'a')),
+        _ => CHARSET.iter().find(|(_, ch)| *ch == c).map(|(b, _)| *b),
+    }
+}
+
+/// Decodes game text up to (not including) the first terminator.
+pub fn decode_text(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for &byte in bytes.iter().take(MAX_TEXT_LENGTH) {
+        if byte == EOS {
+            break;
+        }
+        if let Some((_, escape)) = CONTROLS.iter().find(|(b, _)| *b == byte) {
+            text.push_str(escape);
+        } else if let Some(c) = decode_char(byte) {
+            text.push(c);
+        } else {
+            text.push_str(&format!("[{:02X}]", byte));
+        }
+    }
+    text
+}
+
+/// Decodes the string starting at `offset` in the ROM.
+pub fn read_text(data: &[u8], offset: usize) -> Result<String> {
+    if offset >= data.len() {
+        bail!("Text offset {:08x} out of bounds", offset);
+    }
+    Ok(decode_text(&data[offset..]))
+}
+
+/// Size in bytes of the string at `offset`, including its terminator.
+/// Used to decide whether edited text still fits in place.
+pub fn text_size(data: &[u8], offset: usize) -> usize {
+    data.get(offset..)
+        .and_then(|rest| rest.iter().take(MAX_TEXT_LENGTH).position(|&b| b == EOS))
+        .map_or(0, |len| len + 1)
+}
+
+/// Encodes text into the game's character set, including the terminator.
+pub fn encode_text(text: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() + 1);
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let escape = format!("\\{}", chars.next().unwrap_or(' '));
+                match CONTROLS.iter().find(|(_, e)| *e == escape) {
+                    Some((b, _)) => bytes.push(*b),
+                    None => bail!("Unknown text escape {}", escape),
+                }
+            }
+            '[' => {
+                let hex: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(b) => bytes.push(b),
+                    Err(_) => bail!("Invalid raw byte [{}]", hex),
+                }
+            }
+            _ => match encode_char(c) {
+                Some(b) => bytes.push(b),
+                None => bail!("Character '{}' has no equivalent in the game's charset", c),
+            },
+        }
+    }
+
+    bytes.push(EOS);
+    Ok(bytes)
+}
+
+/// Encodes text into a fixed-size field (names), padding after the terminator.
+pub fn encode_fixed_text(text: &str, size: usize) -> Result<Vec<u8>> {
+    let mut bytes = encode_text(text)?;
+    if bytes.len() > size {
+        bail!("\"{}\" is longer than {} characters", text, size - 1);
+    }
+    bytes.resize(size, 0x00);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_round_trip() {
+        // "POUND!" + newline + raw control byte FC + terminator
+        let bytes = vec![0xCA, 0xC9, 0xCF, 0xC8, 0xBE, 0xAB, 0xFE, 0xFC, 0xFF];
+        let text = decode_text(&bytes);
+        assert_eq!(text, "POUND!\\n[FC]");
+        assert_eq!(encode_text(&text).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_encode_fixed_text_pads() {
+        let bytes = encode_fixed_text("Ab1", 6).unwrap();
+        assert_eq!(bytes, vec![0xBB, 0xD6, 0xA2, 0xFF, 0x00, 0x00]);
+        assert!(encode_fixed_text("TOO LONG", 4).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Game ticks (1/60 s) between two animation frames, used when the callback's timer
+/// check is not recognised. Most vanilla FireRed/Emerald animations advance every 16 ticks.
+pub const DEFAULT_FRAME_INTERVAL: u16 = 16;
+
+/// Start of BG tile VRAM, where the animation routines copy frame graphics to.
+const BG_VRAM: u32 = 0x06000000;
+const BG_VRAM_END: u32 = 0x06010000;
+
+/// How deep calls are followed from the tileset's animation callback.
+/// Vanilla is init -> per-frame callback -> per-animation queue function.
+const MAX_CALL_DEPTH: usize = 3;
+/// Upper bound on the size of a single animation routine.
+const MAX_FUNCTION_SIZE: usize = 0x200;
+const MAX_FRAMES: usize = 16;
+
+/// One animated range of tiles, e.g. the water or flower tiles of a tileset.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TileAnimation {
+    /// ROM pointers to the uncompressed 4bpp graphics of each frame, in play order.
+    pub frames: Vec<u32>,
+    /// First tile (in BG tile numbering) the frames are copied over.
+    pub dest_tile: u16,
+    pub tile_count: u16,
+    pub interval: u16,
+}
+
+impl TileAnimation {
+    /// Returns the graphics of the frame shown at `tick`.
+    pub fn frame_at(&self, tick: u32) -> u32 {
+        self.frames[(tick / self.interval as u32) as usize % self.frames.len()]
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn is_rom_pointer(value: u32, data: &[u8]) -> bool {
+    (0x08000000..0x0A000000).contains(&value) && ((value & 0x01FFFFFF) as usize) < data.len()
+}
+
+/// Literals and call targets found in one THUMB routine.
+/// Each call carries the frame interval of the timer check guarding it, if any.
+#[derive(Default)]
+struct RoutineRefs {
+    literals: Vec<u32>,
+    calls: Vec<(usize, Option<u16>)>,
+}
+
+/// Walks the THUMB code at `start`, collecting PC-relative literal loads and BL targets.
+/// Code ends where the first literal pool begins, or at `MAX_FUNCTION_SIZE`.
+///
+/// Animation callbacks call each queue routine behind a `timer % N == k` check, which
+/// compiles to `movs rX, #N-1` + `ands` for powers of two, or to `movs r1, #N` before
+/// a call to the modulo helper otherwise. The last such N seen is the interval of the
+/// calls that follow it.
+fn scan_routine(data: &[u8], start: usize) -> RoutineRefs {
+    let mut refs = RoutineRefs::default();
+    let mut end = (start + MAX_FUNCTION_SIZE).min(data.len());
+    let mut pc = start;
+    let mut previous = 0u16;
+    let mut interval = None;
+
+    while pc + 2 <= end {
+        let Some(op) = read_u16(data, pc) else {
+            break;
+        };
+
+        if op & 0xFFC0 == 0x4000 && previous & 0xF800 == 0x2000 {
+            // movs rM, #N-1 then ands rX, rY with rM as an operand takes the timer modulo N
+            let (masked, mask) = ((previous >> 8) & 7, previous & 0xFF);
+            let operands = [op & 7, (op >> 3) & 7];
+            if operands.contains(&masked) && mask > 0 && (mask + 1).is_power_of_two() {
+                interval = Some(mask + 1);
+            }
+        }
+
+        if op & 0xF800 == 0x4800 {
+            // ldr rX, [pc, #imm]
+            let addr = ((pc + 4) & !3) + ((op & 0xFF) as usize) * 4;
+            if let Some(value) = read_u32(data, addr) {
+                refs.literals.push(value);
+            }
+            if addr > pc {
+                end = end.min(addr);
+            }
+        } else if op & 0xF800 == 0xF000 {
+            // bl: two halfwords carrying a 22-bit signed offset
+            if let Some(low) = read_u16(data, pc + 2) {
+                if low & 0xF800 == 0xF800 {
+                    let high = (((op & 0x7FF) as i32) << 21) >> 9;
+                    let target = pc as i64 + 4 + high as i64 + (((low & 0x7FF) as i64) << 1);
+                    // movs r1, #N just before a call passes the divisor to the modulo helper
+                    if previous & 0xFF00 == 0x2100 && previous & 0xFF > 1 {
+                        interval = Some(previous & 0xFF);
+                    }
+                    if target >= 0 && (target as usize) < data.len() {
+                        refs.calls.push((target as usize, interval));
+                    }
+                    pc += 2;
+                }
+            }
+        }
+        previous = op;
+        pc += 2;
+    }
+
+    refs
+}
+
+/// Reads a frame table: consecutive ROM pointers to graphics.
+fn read_frame_table(data: &[u8], offset: usize) -> Vec<u32> {
+    let mut frames = Vec::new();
+    while frames.len() < MAX_FRAMES {
+        match read_u32(data, offset + frames.len() * 4) {
+            Some(ptr) if is_rom_pointer(ptr, data) && ptr & 1 == 0 => frames.push(ptr),
+            _ => break,
+        }
+    }
+    frames
+}
+
+/// Frames are stored back to back, so the smallest gap between two distinct
+/// frames is the size of one frame.
+fn frame_size(frames: &[u32]) -> Option<u32> {
+    let mut sorted: Vec<u32> = frames.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    sorted.windows(2).map(|w| w[1] - w[0]).min()
+}
+
+/// Identifies the tile animations installed by a tileset's animation callback.
+///
+/// Vanilla tilesets point `anim_ptr` at an init routine which installs a per-frame
+/// callback; that callback calls one queue routine per animation, and each queue
+/// routine loads a frame table and a destination in BG VRAM from its literal pool.
+/// The routines are walked following that pattern, so tilesets using custom
+/// animation code may not be recognised. Each animation's interval is read from the
+/// timer check in front of the call to its queue routine.
+pub fn find_tile_animations(data: &[u8], anim_ptr: u32) -> Vec<TileAnimation> {
+    let mut animations = Vec::new();
+    if !is_rom_pointer(anim_ptr, data) {
+        return animations;
+    }
+
+    let mut visited = HashSet::new();
+    let mut pending = vec![((anim_ptr & 0x01FFFFFE) as usize, 0, None)];
+
+    while let Some((routine, depth, interval)) = pending.pop() {
+        if !visited.insert(routine) {
+            continue;
+        }
+        let refs = scan_routine(data, routine);
+
+        let mut tables = Vec::new();
+        let mut dest = None;
+        for &value in &refs.literals {
+            if (BG_VRAM..BG_VRAM_END).contains(&value) {
+                dest = Some(value);
+            } else if is_rom_pointer(value, data) {
+                if value & 1 == 1 {
+                    // THUMB function pointer, e.g. the callback being installed
+                    if depth < MAX_CALL_DEPTH {
+                        pending.push(((value & 0x01FFFFFE) as usize, depth + 1, interval));
+                    }
+                } else {
+                    let frames = read_frame_table(data, (value & 0x01FFFFFF) as usize);
+                    if frames.len() >= 2 {
+                        tables.push(frames);
+                    }
+                }
+            }
+        }
+
+        if let (Some(dest), [frames]) = (dest, tables.as_slice()) {
+            if let Some(size) = frame_size(frames) {
+                animations.push(TileAnimation {
+                    frames: frames.clone(),
+                    dest_tile: ((dest - BG_VRAM) / 32) as u16,
+                    tile_count: (size / 32).min(256) as u16,
+                    interval: interval.unwrap_or(DEFAULT_FRAME_INTERVAL),
+                });
+            }
+        }
+
+        if depth < MAX_CALL_DEPTH {
+            pending.extend(
+                refs.calls.iter().map(|&(target, call_interval)| {
+                    (target, depth + 1, call_interval.or(interval))
+                }),
+            );
+        }
+    }
+
+    animations.sort_by_key(|a| a.dest_tile);
+    animations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_queue_routine() {
+        // A queue routine at 0x00:
+        //   ldr r0, [pc, #0]   (48 00) -> frame table literal at 0x04
+        //   ldr r1, [pc, #4]   (49 01) -> VRAM literal at 0x08
+        let mut rom = vec![0u8; 0x40];
+        rom[0x00..0x02].copy_from_slice(&0x4800u16.to_le_bytes());
+        rom[0x02..0x04].copy_from_slice(&0x4901u16.to_le_bytes());
+        rom[0x04..0x08].copy_from_slice(&0x08000010u32.to_le_bytes());
+        rom[0x08..0x0C].copy_from_slice(&0x06003F80u32.to_le_bytes());
+        // Frame table at 0x10: frame 0, frame 1, frame 0 (4 tiles each)
+        rom[0x10..0x14].copy_from_slice(&0x08000100u32.to_le_bytes());
+        rom[0x14..0x18].copy_from_slice(&0x08000180u32.to_le_bytes());
+        rom[0x18..0x1C].copy_from_slice(&0x08000100u32.to_le_bytes());
+        rom.resize(0x200, 0);
+
+        let animations = find_tile_animations(&rom, 0x08000001);
+        assert_eq!(animations.len(), 1);
+        assert_eq!(animations[0].dest_tile, 508);
+        assert_eq!(animations[0].tile_count, 4);
+        assert_eq!(
+            animations[0].frames,
+            vec![0x08000100, 0x08000180, 0x08000100]
+        );
+        assert_eq!(animations[0].frame_at(16), 0x08000180);
+        assert_eq!(animations[0].interval, DEFAULT_FRAME_INTERVAL);
+
+        // A callback at 0x40 calling it behind `timer % 8 == 0`:
+        //   movs r1, #7        (21 07)
+        //   ands r1, r0        (40 01)
+        //   cmp r1, #0         (29 00)
+        //   bne +2             (d1 01)
+        //   bl 0x00            (f7 ff ff da)
+        rom[0x40..0x42].copy_from_slice(&0x2107u16.to_le_bytes());
+        rom[0x42..0x44].copy_from_slice(&0x4001u16.to_le_bytes());
+        rom[0x44..0x46].copy_from_slice(&0x2900u16.to_le_bytes());
+        rom[0x46..0x48].copy_from_slice(&0xD101u16.to_le_bytes());
+        rom[0x48..0x4A].copy_from_slice(&0xF7FFu16.to_le_bytes());
+        rom[0x4A..0x4C].copy_from_slice(&0xFFDAu16.to_le_bytes());
+
+        let animations = find_tile_animations(&rom, 0x08000041);
+        assert_eq!(animations.len(), 1);
+        assert_eq!(animations[0].interval, 8);
+    }
+}
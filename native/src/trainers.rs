@@ -0,0 +1,209 @@
+use crate::structures::Trainer;
+use crate::text::{decode_text, encode_fixed_text};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+pub const TRAINER_SIZE: usize = 40;
+pub const TRAINER_NAME_SIZE: usize = 12;
+/// Most Pokémon a trainer party can hold.
+pub const MAX_PARTY_SIZE: usize = 6;
+
+/// The four layouts of a trainer's party entries, selected by `Trainer::party_flags`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PartyFormat {
+    NoItemDefaultMoves,
+    NoItemCustomMoves,
+    ItemDefaultMoves,
+    ItemCustomMoves,
+}
+
+impl PartyFormat {
+    pub fn from_flags(flags: u8) -> Self {
+        match flags & 0x3 {
+            0 => PartyFormat::NoItemDefaultMoves,
+            1 => PartyFormat::NoItemCustomMoves,
+            2 => PartyFormat::ItemDefaultMoves,
+            _ => PartyFormat::ItemCustomMoves,
+        }
+    }
+
+    pub fn flags(self) -> u8 {
+        match self {
+            PartyFormat::NoItemDefaultMoves => 0,
+            PartyFormat::NoItemCustomMoves => 1,
+            PartyFormat::ItemDefaultMoves => 2,
+            PartyFormat::ItemCustomMoves => 3,
+        }
+    }
+
+    pub fn has_item(self) -> bool {
+        self.flags() & 2 != 0
+    }
+
+    pub fn has_moves(self) -> bool {
+        self.flags() & 1 != 0
+    }
+
+    /// Size of one party entry.
+    pub fn entry_size(self) -> usize {
+        if self.has_moves() {
+            16
+        } else {
+            8
+        }
+    }
+}
+
+/// One Pokémon of a trainer's party.
+/// `held_item` and `moves` are only stored when the party format has them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrainerMon {
+    pub iv: u16,
+    pub level: u16,
+    pub species: u16,
+    pub held_item: u16,
+    pub moves: Vec<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrainerInfo {
+    pub trainer_class: u8,
+    pub encounter_music: u8,
+    pub is_female: bool,
+    pub trainer_pic: u8,
+    pub name: String,
+    pub items: Vec<u16>,
+    pub double_battle: bool,
+    pub ai_flags: u32,
+    pub party_format: PartyFormat,
+    pub party: Vec<TrainerMon>,
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+/// Decodes `count` party entries.
+pub fn decode_party(bytes: &[u8], format: PartyFormat, count: usize) -> Result<Vec<TrainerMon>> {
+    let size = format.entry_size();
+    if bytes.len() < count * size {
+        bail!("Trainer party data too short");
+    }
+
+    Ok(bytes
+        .chunks_exact(size)
+        .take(count)
+        .map(|entry| {
+            // Held item sits before the moves; without an item the moves start at 6
+            let moves_start = if format.has_item() { 8 } else { 6 };
+            TrainerMon {
+                iv: u16_at(entry, 0),
+                level: u16_at(entry, 2),
+                species: u16_at(entry, 4),
+                held_item: if format.has_item() {
+                    u16_at(entry, 6)
+                } else {
+                    0
+                },
+                moves: if format.has_moves() {
+                    (0..4).map(|i| u16_at(entry, moves_start + i * 2)).collect()
+                } else {
+                    Vec::new()
+                },
+            }
+        })
+        .collect())
+}
+
+pub fn encode_party(party: &[TrainerMon], format: PartyFormat) -> Result<Vec<u8>> {
+    if party.is_empty() || party.len() > MAX_PARTY_SIZE {
+        bail!("A trainer party needs 1 to {} Pokémon", MAX_PARTY_SIZE);
+    }
+
+    let mut bytes = Vec::with_capacity(party.len() * format.entry_size());
+    for mon in party {
+        let mut entry = Vec::with_capacity(format.entry_size());
+        entry.extend(mon.iv.to_le_bytes());
+        entry.extend(mon.level.to_le_bytes());
+        entry.extend(mon.species.to_le_bytes());
+        if format.has_item() {
+            entry.extend(mon.held_item.to_le_bytes());
+        }
+        if format.has_moves() {
+            if mon.moves.len() != 4 {
+                bail!("Custom move sets need exactly 4 moves");
+            }
+            entry.extend(mon.moves.iter().flat_map(|m| m.to_le_bytes()));
+        }
+        entry.resize(format.entry_size(), 0);
+        bytes.extend(entry);
+    }
+    Ok(bytes)
+}
+
+impl TrainerInfo {
+    pub fn from_trainer(trainer: &Trainer, party: Vec<TrainerMon>) -> Self {
+        Self {
+            trainer_class: trainer.trainer_class,
+            encounter_music: trainer.encounter_music_gender & 0x7F,
+            is_female: trainer.encounter_music_gender & 0x80 != 0,
+            trainer_pic: trainer.trainer_pic,
+            name: decode_text(&trainer.name),
+            items: trainer.items.clone(),
+            double_battle: trainer.double_battle != 0,
+            ai_flags: trainer.ai_flags,
+            party_format: PartyFormat::from_flags(trainer.party_flags),
+            party,
+        }
+    }
+
+    /// Builds the table entry, pointing at a party stored at `party_ptr`.
+    pub fn to_trainer(&self, party_ptr: u32) -> Result<Trainer> {
+        if self.items.len() != 4 {
+            bail!("Trainers carry exactly 4 item slots");
+        }
+        Ok(Trainer {
+            party_flags: self.party_format.flags(),
+            trainer_class: self.trainer_class,
+            encounter_music_gender: (self.encounter_music & 0x7F) | ((self.is_female as u8) << 7),
+            trainer_pic: self.trainer_pic,
+            name: encode_fixed_text(&self.name, TRAINER_NAME_SIZE)?,
+            items: self.items.clone(),
+            double_battle: self.double_battle as u32,
+            ai_flags: self.ai_flags,
+            party_size: self.party.len() as u32,
+            party_ptr,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_party_round_trip_all_formats() {
+        let party = vec![TrainerMon {
+            iv: 255,
+            level: 50,
+            species: 6,
+            held_item: 0x8B,
+            moves: vec![53, 17, 163, 14],
+        }];
+
+        for format in [
+            PartyFormat::NoItemDefaultMoves,
+            PartyFormat::NoItemCustomMoves,
+            PartyFormat::ItemDefaultMoves,
+            PartyFormat::ItemCustomMoves,
+        ] {
+            let bytes = encode_party(&party, format).unwrap();
+            assert_eq!(bytes.len(), format.entry_size());
+
+            let decoded = decode_party(&bytes, format, 1).unwrap();
+            assert_eq!(decoded[0].species, 6);
+            assert_eq!(decoded[0].held_item == 0x8B, format.has_item());
+            assert_eq!(decoded[0].moves.len() == 4, format.has_moves());
+        }
+    }
+}